@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+use llk::prelude::*;
+
+const ITERATIONS: u32 = 20;
+
+fn measure<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let term_symbols = vec!['a', '+', '(', ')'].drain(..).collect();
+    let nterm_symbols = vec!['S', 'E', 'R', 'T'].drain(..).collect();
+    let productions = vec![
+        ('S', Some("E".to_string())),
+        ('E', Some("TR".to_string())),
+        ('R', Some("+TR".to_string())),
+        ('R', None),
+        ('T', Some("(E)".to_string())),
+        ('T', Some("a".to_string())),
+    ];
+
+    let grammar = LlkGrammar::new(term_symbols, nterm_symbols, 'S', 1, productions).unwrap();
+    let parser = LlkParser::new(grammar);
+
+    for terms in &[1_000, 10_000, 50_000] {
+        let input = vec!["(a+a)"; *terms].join("+");
+
+        assert!(parser.recognize(&input));
+        assert!(parser.parse(&input).is_ok());
+
+        let recognize_time = measure(|| {
+            parser.recognize(&input);
+        });
+        let parse_time = measure(|| {
+            parser.parse(&input).unwrap();
+        });
+
+        println!(
+            "{:>8} symbols: recognize {:>10.3?}, parse {:>10.3?}",
+            input.len(),
+            recognize_time,
+            parse_time
+        );
+    }
+}
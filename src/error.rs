@@ -8,6 +8,22 @@ pub enum LlkError {
     IllegalOperation(String),
     DerivationFailed(String),
     GrammarFromJsonFailed(String),
+    InputReadFailed(String),
+    TreeDeserializationFailed(String),
+    InvalidTree(String),
+    UnexpectedSymbol {
+        symbol: char,
+        position: usize,
+        expected: Vec<String>,
+    },
+    MissingSymbol {
+        symbol: char,
+        position: usize,
+    },
+    UnexpectedEndOfInput {
+        position: usize,
+        expected: Vec<String>,
+    },
 }
 
 impl fmt::Display for LlkError {
@@ -28,10 +44,76 @@ impl fmt::Display for LlkError {
                 write!(f, "{}: derivation failed: {}", ERROR_TYPE_NAME, description)
             }
             LlkError::GrammarFromJsonFailed(description) => {
-                write!(f, "{}: parsing grammar from JSON failed: {}", ERROR_TYPE_NAME, description)
+                write!(
+                    f,
+                    "{}: parsing grammar from JSON failed: {}",
+                    ERROR_TYPE_NAME, description
+                )
+            }
+            LlkError::InputReadFailed(description) => {
+                write!(
+                    f,
+                    "{}: reading input failed: {}",
+                    ERROR_TYPE_NAME, description
+                )
+            }
+            LlkError::TreeDeserializationFailed(description) => {
+                write!(
+                    f,
+                    "{}: deserializing tree failed: {}",
+                    ERROR_TYPE_NAME, description
+                )
+            }
+            LlkError::InvalidTree(description) => {
+                write!(f, "{}: invalid tree: {}", ERROR_TYPE_NAME, description)
+            }
+            LlkError::UnexpectedSymbol {
+                symbol,
+                position,
+                expected,
+            } => {
+                write!(
+                    f,
+                    "{}: unexpected symbol '{}' at position {}, expected {}",
+                    ERROR_TYPE_NAME,
+                    symbol.escape_default(),
+                    position,
+                    format_expected(expected)
+                )
+            }
+            LlkError::MissingSymbol { symbol, position } => {
+                write!(
+                    f,
+                    "{}: missing symbol '{}' at position {}",
+                    ERROR_TYPE_NAME,
+                    symbol.escape_default(),
+                    position
+                )
+            }
+            LlkError::UnexpectedEndOfInput { position, expected } => {
+                write!(
+                    f,
+                    "{}: unexpected end of input at position {}, expected {}",
+                    ERROR_TYPE_NAME,
+                    position,
+                    format_expected(expected)
+                )
             }
         }
     }
 }
 
 impl Error for LlkError {}
+
+fn format_expected(expected: &[String]) -> String {
+    let expected: Vec<String> = expected
+        .iter()
+        .map(|s| format!("'{}'", s.escape_default()))
+        .collect();
+
+    match expected.len() {
+        0 => "nothing".to_string(),
+        1 => expected[0].clone(),
+        _ => format!("one of {}", expected.join(", ")),
+    }
+}
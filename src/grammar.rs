@@ -1,15 +1,27 @@
+pub mod actions;
+pub mod attributes;
+pub mod char_class;
+pub mod completion;
+pub mod listener;
 pub mod parser;
+pub mod skip_rule;
+pub mod span;
 pub mod tree;
+pub mod validation;
 
+mod dot;
 mod grammar_json;
 
 use std::collections::HashMap;
 use std::collections::HashSet;
 
 use crate::error::LlkError;
+use char_class::LlkCharClass;
+use skip_rule::LlkSkipRule;
 
 type LlkProduction = (char, Option<String>);
-type LlkLut = HashMap<(char, String), String>;
+/* LUT rows by non-terminal symbol, each mapping lookahead strings to productions */
+type LlkLut = HashMap<char, HashMap<String, usize>>;
 
 pub struct LlkGrammar {
     term_symbols: HashSet<char>,
@@ -17,10 +29,16 @@ pub struct LlkGrammar {
     start_symbol: char,
     lookahead: usize,
     productions: Vec<LlkProduction>,
+    char_classes: HashMap<char, LlkCharClass>,
+    skip_rules: Vec<LlkSkipRule>,
+    case_insensitive: bool,
+    labels: HashMap<usize, String>,
 }
 
 impl LlkGrammar {
     const EOF: char = '\0';
+    /* Unicode noncharacter standing for the LHS of entry point pseudo-production */
+    const ENTRY: char = '\u{FDD0}';
     const MIN_LOOKAHEAD: usize = 1;
     const MAX_LOOKAHEAD: usize = 16;
 
@@ -32,7 +50,7 @@ impl LlkGrammar {
         productions: Vec<LlkProduction>,
     ) -> Result<LlkGrammar, LlkError> {
         /* Replace empty production RHS strings with None */
-        let productions = LlkGrammar::normalize_productions(productions, start_symbol); 
+        let productions = LlkGrammar::normalize_productions(productions);
 
         let grammar = LlkGrammar {
             term_symbols,
@@ -40,6 +58,10 @@ impl LlkGrammar {
             start_symbol,
             lookahead,
             productions,
+            char_classes: HashMap::new(),
+            skip_rules: Vec::new(),
+            case_insensitive: false,
+            labels: HashMap::new(),
         };
 
         grammar_assert::assert_grammar(&grammar)?;
@@ -47,6 +69,91 @@ impl LlkGrammar {
         Ok(grammar)
     }
 
+    pub fn with_char_classes(
+        mut self,
+        char_classes: HashMap<char, LlkCharClass>,
+    ) -> Result<LlkGrammar, LlkError> {
+        if let Some(symbol) = char_classes
+            .keys()
+            .find(|symbol| !self.term_symbols.contains(symbol))
+        {
+            return Err(LlkError::InvalidGrammar(format!(
+                "character class symbol '{}' is not a terminal symbol",
+                symbol
+            )));
+        }
+
+        if self.case_insensitive {
+            self.char_classes.extend(
+                char_classes
+                    .iter()
+                    .map(|(symbol, char_class)| (*symbol, char_class.case_insensitive())),
+            );
+        } else {
+            self.char_classes.extend(char_classes);
+        }
+
+        /* Classes may overlap, so production choise sets must be checked again */
+        grammar_assert::assert_grammar(&self)?;
+
+        Ok(self)
+    }
+
+    pub fn with_skip_rules(mut self, skip_rules: Vec<LlkSkipRule>) -> Result<LlkGrammar, LlkError> {
+        self.skip_rules.extend(skip_rules);
+
+        grammar_assert::assert_skip_rules(&self)?;
+
+        Ok(self)
+    }
+
+    pub fn with_case_insensitive(mut self) -> Result<LlkGrammar, LlkError> {
+        self.case_insensitive = true;
+
+        for char_class in self.char_classes.values_mut() {
+            *char_class = char_class.case_insensitive();
+        }
+
+        /* Terminal symbols that differ in case only may collide now */
+        grammar_assert::assert_grammar(&self)?;
+        grammar_assert::assert_skip_rules(&self)?;
+
+        Ok(self)
+    }
+
+    /* Name productions, referred to by their indices in the order of definition */
+    pub fn with_labels(mut self, labels: HashMap<usize, String>) -> Result<LlkGrammar, LlkError> {
+        for (index, label) in labels {
+            if index >= self.productions.len() {
+                return Err(LlkError::InvalidGrammar(format!(
+                    "label \"{}\" refers to non-existent production {}",
+                    label, index
+                )));
+            }
+            if self.labeled_production(&label).is_some_and(|i| i != index) {
+                return Err(LlkError::InvalidGrammar(format!(
+                    "duplicate production label \"{}\"",
+                    label
+                )));
+            }
+
+            self.labels.insert(index, label);
+        }
+
+        Ok(self)
+    }
+
+    pub fn production_label(&self, index: usize) -> Option<&str> {
+        self.labels.get(&index).map(|label| label.as_str())
+    }
+
+    pub fn labeled_production(&self, label: &str) -> Option<usize> {
+        self.labels
+            .iter()
+            .find(|(_index, production_label)| *production_label == label)
+            .map(|(index, _production_label)| *index)
+    }
+
     pub fn from_json(json_string: &str) -> Result<LlkGrammar, LlkError> {
         grammar_json::parse_grammar_json(json_string)
     }
@@ -59,7 +166,7 @@ impl LlkGrammar {
         if string.is_empty() {
             /* Calculate FIRST set of ε */
             first_set.insert(None);
-        } else if string.len() == 1 {
+        } else if string.chars().count() == 1 {
             /* Calculate FIRST set of a symbol */
             let symbol = string.chars().nth(0).unwrap();
 
@@ -75,8 +182,7 @@ impl LlkGrammar {
                 }
 
                 /* Get all non-terminal non-ε derivatives */
-                let derivatives: Vec<String> =
-                    self.derive(symbol).drain(..).filter_map(|d| d).collect();
+                let derivatives: Vec<String> = self.derive(symbol).drain(..).flatten().collect();
 
                 /* Scan through all derivatives */
                 for derivative in derivatives {
@@ -93,11 +199,7 @@ impl LlkGrammar {
                         first_set.insert(None);
                     } else {
                         /* Get all k-prefixes of non-ε suffix of the production's RHS */
-                        first_set.extend(
-                            self.get_term_prefixes(non_empty_suffix)
-                                .drain()
-                                .map(|s| Some(s)),
-                        )
+                        first_set.extend(self.get_term_prefixes(non_empty_suffix).drain().map(Some))
                     }
                 }
             }
@@ -111,7 +213,7 @@ impl LlkGrammar {
                 first_set.insert(None);
             };
 
-            first_set.extend(self.get_term_prefixes(string).drain().map(|s| Some(s)))
+            first_set.extend(self.get_term_prefixes(string).drain().map(Some))
         }
 
         Ok(first_set)
@@ -124,49 +226,104 @@ impl LlkGrammar {
             ));
         }
 
-        fn inner<'a>(
-            grammar: &'a LlkGrammar,
-            nterm: char,
-            visited: &mut HashSet<&'a LlkProduction>,
-        ) -> HashSet<String> {
-            let mut follow_set = HashSet::new();
+        Ok(self.follow_from(self.start_symbol, nterm))
+    }
+}
+
+impl LlkGrammar {
+    /*
+     * FOLLOW sets of all the non-terminal symbols in sentences derived from the entry
+     * symbol. Sentences are derived by the pseudo-production ENTRY → entry EOF, so the
+     * end marker follows the entry symbol, the start symbol being the default one.
+     * Prefixes of the symbols following an occurrence are completed to the lookahead
+     * length with the FOLLOW set of the production's LHS, till nothing changes
+     */
+    fn get_follow_sets(&self, entry: char) -> HashMap<char, HashSet<String>> {
+        let entry_production = (
+            LlkGrammar::ENTRY,
+            Some(format!("{}{}", entry, LlkGrammar::EOF)),
+        );
+
+        /* Each occurrence of a non-terminal symbol with the LHS and the FIRST set of what follows it */
+        let mut occurrences: Vec<(char, char, HashSet<String>)> = Vec::new();
+        for (prod_nterm, derivative) in self
+            .productions
+            .iter()
+            .chain(std::iter::once(&entry_production))
+        {
+            let derivative = derivative.as_deref().unwrap_or_default();
+
+            for (index, symbol) in derivative.char_indices() {
+                if self.is_nterm(symbol) {
+                    let suffix = &derivative[index + symbol.len_utf8()..];
+                    let first_set = self
+                        .first(suffix)
+                        .unwrap()
+                        .drain()
+                        .map(|s| s.unwrap_or_default())
+                        .collect();
 
-            for production in &grammar.productions {
-                if let None = production.1 {
-                    continue;
+                    occurrences.push((symbol, *prod_nterm, first_set));
                 }
+            }
+        }
 
-                let prod_nterm = production.0;
-                let prod_derivative = (&production.1).as_ref().unwrap();
+        let mut follow_sets: HashMap<char, HashSet<String>> = HashMap::new();
+        let mut changed = true;
 
-                if let Some(suffixes) = grammar.get_nterm_suffixes(prod_derivative, nterm) {
-                    let mut suffixes_first_set: HashSet<Option<String>> = suffixes
-                        .iter()
-                        .flat_map(|s| grammar.first(s).unwrap())
-                        .collect();
+        while changed {
+            changed = false;
 
-                    if prod_nterm != nterm
-                        && !visited.contains(production)
-                        && (prod_derivative.ends_with(nterm) || suffixes_first_set.contains(&None))
+            for (nterm, prod_nterm, first_set) in &occurrences {
+                let prod_follow_set = follow_sets.get(prod_nterm).cloned().unwrap_or_default();
+                let follow_set = follow_sets.entry(*nterm).or_default();
+
+                for prefix in first_set {
+                    if prefix.chars().count() >= self.lookahead || prefix.ends_with(LlkGrammar::EOF)
                     {
-                        visited.insert(production);
-                        follow_set.extend(inner(grammar, prod_nterm, visited));
+                        changed |= follow_set.insert(prefix.clone());
+                    } else {
+                        for suffix in &prod_follow_set {
+                            /* Truncate to lookahead length in symbols, not in bytes */
+                            let string = format!("{}{}", prefix, suffix)
+                                .chars()
+                                .take(self.lookahead)
+                                .collect();
+                            changed |= follow_set.insert(string);
+                        }
                     }
-
-                    follow_set.extend(suffixes_first_set.drain().filter_map(|s| s))
                 }
             }
-
-            follow_set
         }
 
-        Ok(inner(self, nterm, &mut HashSet::new()))
+        follow_sets
     }
-}
 
-impl LlkGrammar {
-    fn choise(&self, production: &LlkProduction) -> HashSet<String> {
-        let prod_nterm = production.0;
+    fn follow_from(&self, entry: char, nterm: char) -> HashSet<String> {
+        self.get_follow_sets(entry)
+            .remove(&nterm)
+            .unwrap_or_default()
+    }
+
+    /* Choise sets of all the productions in the order of definition */
+    fn choise_sets_from(&self, entry: char) -> Vec<HashSet<String>> {
+        let follow_sets = self.get_follow_sets(entry);
+        let no_follow = HashSet::new();
+
+        self.productions
+            .iter()
+            .map(|production| {
+                let follow_set = follow_sets.get(&production.0).unwrap_or(&no_follow);
+                self.get_choise_set(production, follow_set)
+            })
+            .collect()
+    }
+
+    fn get_choise_set(
+        &self,
+        production: &LlkProduction,
+        follow_set: &HashSet<String>,
+    ) -> HashSet<String> {
         let prod_derivative = if let Some(derivative) = &production.1 {
             derivative.clone()
         } else {
@@ -179,7 +336,6 @@ impl LlkGrammar {
             .drain()
             .map(|s| s.unwrap_or_default())
             .collect();
-        let follow_set: HashSet<String> = self.follow(prod_nterm).unwrap();
         let choise_set: HashSet<String> = if follow_set.is_empty() {
             first_set
         } else {
@@ -187,11 +343,13 @@ impl LlkGrammar {
                 .iter()
                 .flat_map(|s| {
                     std::iter::repeat(s)
-                        .zip(&follow_set)
+                        .zip(follow_set)
                         .map(|(prefix, suffix)| {
-                            let mut choise_string = format!("{}{}", prefix, suffix);
-                            choise_string.truncate(self.lookahead);
-                            choise_string
+                            /* Truncate to lookahead length in symbols, not in bytes */
+                            format!("{}{}", prefix, suffix)
+                                .chars()
+                                .take(self.lookahead)
+                                .collect::<String>()
                         })
                 })
                 .collect()
@@ -208,12 +366,97 @@ impl LlkGrammar {
         self.nterm_symbols.contains(&symbol)
     }
 
+    fn term_matches(&self, term: char, symbol: char) -> bool {
+        if let Some(char_class) = self.char_classes.get(&term) {
+            char_class.contains(symbol)
+        } else {
+            self.fold_case(term) == self.fold_case(symbol)
+        }
+    }
+
+    fn fold_case(&self, symbol: char) -> char {
+        if !self.case_insensitive {
+            return symbol;
+        }
+
+        /* Simple case folding: keep symbols without single symbol lowercase mapping */
+        let mut lowercase = symbol.to_lowercase();
+        match (lowercase.next(), lowercase.next()) {
+            (Some(folded), None) => folded,
+            _ => symbol,
+        }
+    }
+
+    fn fold_term_string(&self, string: &str) -> String {
+        string
+            .chars()
+            .map(|symbol| {
+                if self.char_classes.contains_key(&symbol) {
+                    symbol
+                } else {
+                    self.fold_case(symbol)
+                }
+            })
+            .collect()
+    }
+
+    fn term_string_matches(&self, term_string: &str, string: &str) -> bool {
+        term_string.chars().count() == string.chars().count()
+            && term_string
+                .chars()
+                .zip(string.chars())
+                .all(|(term, symbol)| self.term_matches(term, symbol))
+    }
+
+    fn terms_intersect(&self, term_a: char, term_b: char) -> bool {
+        match (
+            self.char_classes.get(&term_a),
+            self.char_classes.get(&term_b),
+        ) {
+            (Some(class_a), Some(class_b)) => class_a.intersects(class_b),
+            (Some(class_a), None) => class_a.contains(term_b),
+            (None, Some(class_b)) => class_b.contains(term_a),
+            (None, None) => self.fold_case(term_a) == self.fold_case(term_b),
+        }
+    }
+
+    fn term_strings_intersect(&self, string_a: &str, string_b: &str) -> bool {
+        string_a.chars().count() == string_b.chars().count()
+            && string_a
+                .chars()
+                .zip(string_b.chars())
+                .all(|(term_a, term_b)| self.terms_intersect(term_a, term_b))
+    }
+
+    fn match_skip(&self, chars: &[char]) -> Option<usize> {
+        self.skip_rules
+            .iter()
+            .find_map(|skip_rule| skip_rule.match_len(chars))
+    }
+
+    fn choise_sets_intersect(&self, set_a: &HashSet<String>, set_b: &HashSet<String>) -> bool {
+        if self.char_classes.is_empty() && !self.case_insensitive {
+            !set_a.is_disjoint(set_b)
+        } else {
+            set_a
+                .iter()
+                .any(|a| set_b.iter().any(|b| self.term_strings_intersect(a, b)))
+        }
+    }
+
     fn derives_epsilon(&self, symbol: char) -> bool {
         !self.is_term(symbol)
             && self
                 .productions
                 .iter()
-                .any(|(nterm, derivative)| *nterm == symbol && matches!(derivative, None))
+                .any(|(nterm, derivative)| *nterm == symbol && derivative.is_none())
+    }
+
+    fn is_nullable(&self, symbol: char) -> bool {
+        self.is_nterm(symbol)
+            && self
+                .first(&symbol.to_string())
+                .is_ok_and(|first_set| first_set.contains(&None))
     }
 
     fn derive(&self, symbol: char) -> Vec<Option<String>> {
@@ -240,7 +483,9 @@ impl LlkGrammar {
                 /* String contains at least one non-terminal symbol */
                 let (prefix, suffix) = string.split_once(leftmost_nterm).unwrap();
 
-                if prefix.len() >= prefix_len {
+                let prefix_symbols_len = prefix.chars().count();
+
+                if prefix_symbols_len >= prefix_len {
                     /* If prefix length is enough return it */
                     vec![format!(
                         "{term_prefix:.limit$}",
@@ -250,7 +495,7 @@ impl LlkGrammar {
                     .drain(..)
                     .collect()
                 } else {
-                    let prefix_rest_len = prefix_len - prefix.len();
+                    let prefix_rest_len = prefix_len - prefix_symbols_len;
 
                     grammar
                         .derive(leftmost_nterm)
@@ -281,26 +526,6 @@ impl LlkGrammar {
 
         inner(self, string, self.lookahead)
     }
-
-    fn get_nterm_suffixes<'a>(&self, string: &'a str, nterm: char) -> Option<Vec<&'a str>> {
-        if string.is_empty() || !string.contains(nterm) {
-            return None;
-        }
-
-        let mut suffixes = Vec::new();
-
-        /* Find all x suffixes in wAx production derivation */
-        let mut current_suffix = string;
-        while let Some((_prefix, suffix)) = current_suffix.split_once(nterm) {
-            if !suffix.is_empty() {
-                /* If production is B => wAx push x to the list */
-                suffixes.push(suffix);
-            }
-            current_suffix = suffix;
-        }
-
-        Some(suffixes)
-    }
 }
 
 impl LlkGrammar {
@@ -313,32 +538,20 @@ impl LlkGrammar {
 
         format!("({} -> {})", nterm, derivative)
     }
-    
-    fn normalize_productions(mut productions: Vec<LlkProduction>, start_symbol: char) -> Vec<LlkProduction> {
-        productions.drain(..).map(|(nterm, derivation)|{
-            if nterm == start_symbol {
-                if let Some(mut string) = derivation {
-                    string.push(LlkGrammar::EOF);
-                    (nterm, Some(string))
-                } else {
-                    (nterm, Some(LlkGrammar::EOF.to_string()))
-                }
-            } else {
-                if let Some(string) = derivation {
-                    if string.is_empty() {
-                        (nterm, None)
-                    } else {
-                        (nterm, Some(string))
-                    }
-                } else {
-                    (nterm, derivation)
-                }
-            }
-        }).collect()
+
+    fn normalize_productions(mut productions: Vec<LlkProduction>) -> Vec<LlkProduction> {
+        productions
+            .drain(..)
+            .map(|(nterm, derivation)| match derivation {
+                Some(string) if string.is_empty() => (nterm, None),
+                derivation => (nterm, derivation),
+            })
+            .collect()
     }
 }
 
 mod grammar_assert {
+    use super::skip_rule::LlkSkipRule;
     use super::LlkGrammar;
     use crate::error::LlkError;
     use std::collections::HashSet;
@@ -374,7 +587,16 @@ mod grammar_assert {
 
     pub(super) fn assert_term_string(grammar: &LlkGrammar, string: &str) -> Result<(), LlkError> {
         for symbol in string.chars() {
-            if !grammar.is_term(symbol) {
+            let matches = if grammar.char_classes.is_empty() && !grammar.case_insensitive {
+                grammar.is_term(symbol)
+            } else {
+                grammar
+                    .term_symbols
+                    .iter()
+                    .any(|term| grammar.term_matches(*term, symbol))
+            };
+
+            if !matches {
                 return Err(LlkError::UnknownSymbol(symbol));
             }
         }
@@ -382,6 +604,61 @@ mod grammar_assert {
         Ok(())
     }
 
+    pub(super) fn assert_skip_rules(grammar: &LlkGrammar) -> Result<(), LlkError> {
+        for skip_rule in &grammar.skip_rules {
+            match skip_rule {
+                LlkSkipRule::Class(skip_class) => {
+                    /* Case insensitive terminals match skipped symbols of either case */
+                    let skip_class = if grammar.case_insensitive {
+                        skip_class.case_insensitive()
+                    } else {
+                        skip_class.clone()
+                    };
+
+                    /* Check that skipped symbols can never be terminal symbols */
+                    let overlapped_term = grammar.term_symbols.iter().find(|term| {
+                        if let Some(char_class) = grammar.char_classes.get(term) {
+                            char_class.intersects(&skip_class)
+                        } else {
+                            skip_class.contains(**term)
+                        }
+                    });
+
+                    if let Some(term) = overlapped_term {
+                        return Err(LlkError::InvalidGrammar(format!(
+                            "skip rule {} overlaps terminal symbol '{}'",
+                            skip_class, term
+                        )));
+                    }
+                }
+                LlkSkipRule::Delimited(open, close) => {
+                    /* Check that delimiters are not empty */
+                    if open.is_empty() || close.is_empty() {
+                        return Err(LlkError::InvalidGrammar(
+                            "skip rule delimiter is empty".to_string(),
+                        ));
+                    }
+                    /* Check that the opening delimiter can never be a terminal sequence */
+                    let overlaps_terms = open.chars().all(|symbol| {
+                        grammar
+                            .term_symbols
+                            .iter()
+                            .any(|term| grammar.term_matches(*term, symbol))
+                    });
+
+                    if overlaps_terms {
+                        return Err(LlkError::InvalidGrammar(format!(
+                            "skip rule delimiter \"{}\" overlaps terminal symbols",
+                            open.escape_default()
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn assert_symbols(grammar: &LlkGrammar) -> Result<(), LlkError> {
         /* Check for empty terminal symbols set */
         if grammar.term_symbols.is_empty() {
@@ -549,28 +826,17 @@ mod grammar_assert {
             .iter()
             .filter_map(|p| {
                 let nterm = p.0;
-                let derivations: Vec<String> =
-                    grammar.derive(nterm).drain(..).filter_map(|d| d).collect();
+                let derivations: Vec<String> = grammar.derive(nterm).drain(..).flatten().collect();
                 let derived_nterm_prefixes: Vec<char> = derivations
                     .iter()
-                    .filter_map(|s| {
-                        if let Some(c) = s.chars().nth(0) {
-                            if grammar.is_nterm(c) {
-                                Some(c)
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        }
-                    })
+                    .filter_map(|s| s.chars().next().filter(|&c| grammar.is_nterm(c)))
                     .collect();
 
                 if derived_nterm_prefixes.iter().any(|c| {
                     grammar
                         .derive(*c)
                         .drain(..)
-                        .filter_map(|d| d)
+                        .flatten()
                         .any(|s| s.starts_with(nterm))
                 }) {
                     Some(LlkGrammar::format_production(p))
@@ -597,12 +863,11 @@ mod grammar_assert {
         }
 
         /* Check is grammar is LL(k) */
+        let choise_sets = grammar.choise_sets_from(grammar.start_symbol);
         for (i, production_a) in grammar.productions.iter().enumerate() {
-            let choise_a = grammar.choise(production_a);
-
-            for production_b in grammar.productions.iter().skip(i + 1) {
+            for (j, production_b) in grammar.productions.iter().enumerate().skip(i + 1) {
                 if production_a.0 == production_b.0
-                    && !choise_a.is_disjoint(&grammar.choise(production_b))
+                    && grammar.choise_sets_intersect(&choise_sets[i], &choise_sets[j])
                 {
                     return Err(LlkError::InvalidGrammar(format!(
                         "grammar rules do not define LL({k}) grammar:\n\
@@ -624,7 +889,7 @@ mod grammar_assert {
         let mut next_reachable_set = HashSet::with_capacity(grammar.nterm_symbols.len() + 1);
         cur_reachable_set.insert(grammar.start_symbol);
 
-        while next_reachable_set.len() != cur_reachable_set.len() {            
+        while next_reachable_set.len() != cur_reachable_set.len() {
             /*
              * Inspect new non-terminal symbols
              * and extend next set with derivative non-terminal symbols
@@ -637,7 +902,7 @@ mod grammar_assert {
                 .flat_map(|c| grammar.derive(*c))
                 .collect::<HashSet<Option<String>>>()
                 .drain()
-                .filter_map(|d| d)
+                .flatten()
                 .collect();
 
             next_reachable_set.extend(
@@ -648,9 +913,7 @@ mod grammar_assert {
             next_reachable_set.extend(&cur_reachable_set);
 
             /* Swap sets */
-            let tmp_set = cur_reachable_set;
-            cur_reachable_set = next_reachable_set;
-            next_reachable_set = tmp_set;
+            std::mem::swap(&mut cur_reachable_set, &mut next_reachable_set);
         }
 
         cur_reachable_set
@@ -662,19 +925,20 @@ mod grammar_assert {
 
         /* Include all the one-step resolvable non-terminal symbols into the initial set */
         cur_resolvable_set.extend(grammar.productions.iter().filter_map(|(c, s)| {
-            if s.is_some()
-                && s.as_ref()
-                    .unwrap()
-                    .chars()
-                    .all(|x| grammar.is_term(x))
-            {
+            /* ε-productions resolve to the empty terminal string */
+            let resolvable = match s {
+                Some(s) => s.chars().all(|x| grammar.is_term(x)),
+                None => true,
+            };
+
+            if resolvable {
                 Some(c)
             } else {
                 None
             }
         }));
 
-        while next_resolvable_set.len() != cur_resolvable_set.len() {            
+        while next_resolvable_set.len() != cur_resolvable_set.len() {
             /*
              * Inspect non-terminal symbols that derives string with non-terminals in the current set
              * and extend next set with them
@@ -682,11 +946,14 @@ mod grammar_assert {
              * V_[k+1] = V_[k] UNION {B | (EXIST a IN V*): B =>* a}
              */
             next_resolvable_set.extend(grammar.productions.iter().filter_map(|(c, s)| {
-                if s.is_some()
-                    && s.as_ref().unwrap().chars().all(|x| {
-                        grammar.is_term(x) || cur_resolvable_set.contains(&x)
-                    })
-                {
+                let resolvable = match s {
+                    Some(s) => s
+                        .chars()
+                        .all(|x| grammar.is_term(x) || cur_resolvable_set.contains(&x)),
+                    None => true,
+                };
+
+                if resolvable {
                     Some(c)
                 } else {
                     None
@@ -694,15 +961,39 @@ mod grammar_assert {
             }));
 
             /* Swap sets */
-            let tmp_set = cur_resolvable_set;
-            cur_resolvable_set = next_resolvable_set;
-            next_resolvable_set = tmp_set;
+            std::mem::swap(&mut cur_resolvable_set, &mut next_resolvable_set);
         }
 
         cur_resolvable_set
     }
 }
 
+/* Sums of 'a' and parenthesized sums, shared by the tests of the parser and the trees */
+#[cfg(test)]
+fn expression_grammar() -> LlkGrammar {
+    let term_symbols = vec!['a', '+', '(', ')'].drain(..).collect();
+    let nterm_symbols = vec!['S', 'E', 'R', 'T'].drain(..).collect();
+    let start_symbol = 'S';
+    let lookahead = 1;
+    let productions = vec![
+        ('S', Some("E".to_string())),
+        ('E', Some("TR".to_string())),
+        ('R', Some("+TR".to_string())),
+        ('R', None),
+        ('T', Some("(E)".to_string())),
+        ('T', Some("a".to_string())),
+    ];
+
+    LlkGrammar::new(
+        term_symbols,
+        nterm_symbols,
+        start_symbol,
+        lookahead,
+        productions,
+    )
+    .unwrap()
+}
+
 #[test]
 fn first_set_test() {
     use std::iter::FromIterator;
@@ -717,6 +1008,10 @@ fn first_set_test() {
             ('A', Some("aA".to_string())),
             ('A', Some("a".to_string())),
         ],
+        char_classes: HashMap::new(),
+        skip_rules: Vec::new(),
+        case_insensitive: false,
+        labels: HashMap::new(),
     };
 
     assert_eq!(
@@ -775,15 +1070,307 @@ fn follow_set_test() {
             ('A', Some("aA".to_string())),
             ('A', Some("a".to_string())),
         ],
+        char_classes: HashMap::new(),
+        skip_rules: Vec::new(),
+        case_insensitive: false,
+        labels: HashMap::new(),
     };
 
-    assert_eq!(grammar.follow('S').unwrap(), HashSet::from_iter(vec![]));
+    assert_eq!(
+        grammar.follow('S').unwrap(),
+        HashSet::from_iter(vec!["\0".to_string()])
+    );
     assert_eq!(
         grammar.follow('A').unwrap(),
-        HashSet::from_iter(vec!["b$".to_string()])
+        HashSet::from_iter(vec!["b$\0".to_string()])
     );
     assert!(matches!(
         grammar.follow('a'),
         Err(LlkError::IllegalOperation(_))
     ));
 }
+
+#[test]
+fn multibyte_symbols_test() {
+    use std::iter::FromIterator;
+
+    let grammar = LlkGrammar {
+        term_symbols: vec!['а', 'б', '∑'].drain(..).collect(),
+        nterm_symbols: vec!['С', 'Α'].drain(..).collect(),
+        start_symbol: 'С',
+        lookahead: 3,
+        productions: vec![
+            ('С', Some("Αб∑".to_string())),
+            ('Α', Some("аΑ".to_string())),
+            ('Α', Some("а".to_string())),
+        ],
+        char_classes: HashMap::new(),
+        skip_rules: Vec::new(),
+        case_insensitive: false,
+        labels: HashMap::new(),
+    };
+
+    assert_eq!(
+        grammar.first("С").unwrap(),
+        HashSet::from_iter(vec![
+            Some("аб∑".to_string()),
+            Some("ааб".to_string()),
+            Some("ааа".to_string())
+        ])
+    );
+    assert_eq!(
+        grammar.first("∑").unwrap(),
+        HashSet::from_iter(vec![Some("∑".to_string())])
+    );
+    assert_eq!(
+        grammar.follow('Α').unwrap(),
+        HashSet::from_iter(vec!["б∑\0".to_string()])
+    );
+    assert_eq!(
+        grammar.choise_sets_from(grammar.start_symbol)[2],
+        HashSet::from_iter(vec!["аб∑".to_string()])
+    );
+}
+
+#[test]
+fn char_class_choise_test() {
+    let char_classes = |classes: &[(char, &str)]| -> HashMap<char, LlkCharClass> {
+        classes
+            .iter()
+            .map(|(symbol, pattern)| (*symbol, LlkCharClass::from_pattern(pattern).unwrap()))
+            .collect()
+    };
+    let new_grammar = |productions: Vec<LlkProduction>| {
+        LlkGrammar::new(
+            vec!['d', 'x', '5'].drain(..).collect(),
+            productions.iter().map(|p| p.0).collect(),
+            'S',
+            1,
+            productions,
+        )
+        .unwrap()
+    };
+
+    /* Digit class collides with a literal digit */
+    let grammar = new_grammar(vec![
+        ('S', Some("A".to_string())),
+        ('A', Some("d".to_string())),
+        ('A', Some("5".to_string())),
+    ]);
+    assert!(matches!(
+        grammar.with_char_classes(char_classes(&[('d', "[0-9]")])),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    /* Digit class does not collide with a letter */
+    let grammar = new_grammar(vec![
+        ('S', Some("A".to_string())),
+        ('A', Some("d".to_string())),
+        ('A', Some("x".to_string())),
+    ]);
+    assert!(grammar
+        .with_char_classes(char_classes(&[('d', "[0-9]")]))
+        .is_ok());
+
+    /* Negated class is disjoint with its complement only */
+    let grammar = new_grammar(vec![
+        ('S', Some("A".to_string())),
+        ('A', Some("d".to_string())),
+        ('A', Some("x".to_string())),
+    ]);
+    assert!(grammar
+        .with_char_classes(char_classes(&[('d', "[0-9]"), ('x', "[^0-9]")]))
+        .is_ok());
+
+    let grammar = new_grammar(vec![
+        ('S', Some("A".to_string())),
+        ('A', Some("d".to_string())),
+        ('A', Some("x".to_string())),
+    ]);
+    assert!(matches!(
+        grammar.with_char_classes(char_classes(&[('d', "[0-9]"), ('x', "[^a-z]")])),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    /* Only terminal symbols can be classes */
+    let grammar = new_grammar(vec![
+        ('S', Some("A".to_string())),
+        ('A', Some("5".to_string())),
+    ]);
+    assert!(matches!(
+        grammar.with_char_classes(char_classes(&[('A', ".")])),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+}
+
+#[test]
+fn skip_rules_test() {
+    let new_grammar = || {
+        LlkGrammar::new(
+            vec!['a', ' ', '#'].drain(..).collect(),
+            vec!['S'].drain(..).collect(),
+            'S',
+            1,
+            vec![('S', Some("a #".to_string()))],
+        )
+        .unwrap()
+    };
+
+    /* Skipped symbols can not be terminal symbols */
+    assert!(matches!(
+        new_grammar().with_skip_rules(vec![LlkSkipRule::Class(
+            LlkCharClass::from_pattern("[ \t]").unwrap()
+        )]),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+    assert!(new_grammar()
+        .with_skip_rules(vec![LlkSkipRule::Class(
+            LlkCharClass::from_pattern("[\t\n]").unwrap()
+        )])
+        .is_ok());
+
+    /* Delimited fragments may start with a terminal symbol but must not be empty */
+    assert!(new_grammar()
+        .with_skip_rules(vec![LlkSkipRule::Delimited(
+            "#!".to_string(),
+            "\n".to_string()
+        )])
+        .is_ok());
+    /* Opening delimiter made of terminal symbols only could hide valid input */
+    assert!(matches!(
+        new_grammar().with_skip_rules(vec![LlkSkipRule::Delimited(
+            "##".to_string(),
+            "\n".to_string()
+        )]),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+    assert!(matches!(
+        new_grammar().with_skip_rules(vec![LlkSkipRule::Delimited(
+            "a #".to_string(),
+            "\n".to_string()
+        )]),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+    assert!(matches!(
+        new_grammar().with_skip_rules(vec![LlkSkipRule::Delimited(
+            String::default(),
+            "\n".to_string()
+        )]),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    let grammar = new_grammar()
+        .with_skip_rules(vec![
+            LlkSkipRule::Class(LlkCharClass::from_pattern("[\t\n]").unwrap()),
+            LlkSkipRule::Delimited("#!".to_string(), "\n".to_string()),
+        ])
+        .unwrap();
+    let chars = |s: &str| s.chars().collect::<Vec<char>>();
+    assert_eq!(grammar.match_skip(&chars("\t\t\na")), Some(3));
+    assert_eq!(grammar.match_skip(&chars("#! a\na")), Some(5));
+    assert_eq!(grammar.match_skip(&chars("# a")), None);
+}
+
+#[test]
+fn case_insensitive_test() {
+    let new_grammar = |productions: Vec<LlkProduction>| {
+        LlkGrammar::new(
+            vec!['a', 'A', 'b', 'd'].drain(..).collect(),
+            vec!['S'].drain(..).collect(),
+            'S',
+            1,
+            productions,
+        )
+        .unwrap()
+    };
+
+    /* Terminal symbols differing in case only collide */
+    let grammar = new_grammar(vec![
+        ('S', Some("a".to_string())),
+        ('S', Some("Ab".to_string())),
+    ]);
+    assert!(matches!(
+        grammar.with_case_insensitive(),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    /* Character classes are case folded too */
+    let grammar = new_grammar(vec![
+        ('S', Some("d".to_string())),
+        ('S', Some("b".to_string())),
+    ])
+    .with_char_classes(
+        vec![('d', LlkCharClass::from_pattern("[A-C]").unwrap())]
+            .drain(..)
+            .collect(),
+    );
+    assert!(grammar.is_ok());
+    assert!(matches!(
+        grammar.unwrap().with_case_insensitive(),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    let grammar = new_grammar(vec![('S', Some("aAb".to_string()))])
+        .with_case_insensitive()
+        .unwrap();
+    assert!(grammar.term_matches('a', 'A') && grammar.term_matches('A', 'a'));
+    assert!(!grammar.term_matches('a', 'b'));
+    assert_eq!(grammar.fold_term_string("AbÆ"), "abæ");
+
+    /* Skipped symbols are case folded as well */
+    let skip_upper_b = || {
+        vec![LlkSkipRule::Class(
+            LlkCharClass::from_pattern("[B]").unwrap(),
+        )]
+    };
+    assert!(new_grammar(vec![('S', Some("aAb".to_string()))])
+        .with_skip_rules(skip_upper_b())
+        .is_ok());
+    assert!(matches!(
+        grammar.with_skip_rules(skip_upper_b()),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+    assert!(matches!(
+        new_grammar(vec![('S', Some("aAb".to_string()))])
+            .with_skip_rules(skip_upper_b())
+            .unwrap()
+            .with_case_insensitive(),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+}
+
+#[test]
+fn resolvable_nterms_test() {
+    let new_grammar = |productions: Vec<LlkProduction>| {
+        LlkGrammar::new(
+            vec!['a', 'b'].drain(..).collect(),
+            vec!['S', 'A'].drain(..).collect(),
+            'S',
+            1,
+            productions,
+        )
+    };
+
+    /* Non-terminal symbol resolves through its ε-production only */
+    assert!(new_grammar(vec![
+        ('S', Some("Ab".to_string())),
+        ('A', Some("aA".to_string())),
+        ('A', None),
+    ])
+    .is_ok());
+    assert!(new_grammar(vec![
+        ('S', Some("Ab".to_string())),
+        ('A', Some("aA".to_string())),
+        ('A', Some(String::new())),
+    ])
+    .is_ok());
+
+    /* Without it the derivation of the symbol never ends */
+    assert!(matches!(
+        new_grammar(vec![
+            ('S', Some("Ab".to_string())),
+            ('A', Some("aA".to_string())),
+        ]),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use super::listener::LlkParseListener;
+use super::span::LlkSpan;
+use super::LlkGrammar;
+use crate::error::LlkError;
+
+type LlkAction<T> = Box<dyn Fn(Vec<T>) -> T>;
+
+/*
+ * Semantic actions computing a value for the LHS of a production from
+ * the values of its RHS symbols. Terminal symbols get their values from
+ * a single function, productions without an action pass up the value
+ * of their only RHS symbol
+ */
+pub struct LlkActions<T> {
+    terminal: Box<dyn Fn(char) -> T>,
+    actions: HashMap<usize, LlkAction<T>>,
+    labeled_actions: HashMap<String, LlkAction<T>>,
+}
+
+impl<T> LlkActions<T> {
+    pub fn new<F>(terminal: F) -> LlkActions<T>
+    where
+        F: Fn(char) -> T + 'static,
+    {
+        LlkActions {
+            terminal: Box::new(terminal),
+            actions: HashMap::new(),
+            labeled_actions: HashMap::new(),
+        }
+    }
+
+    pub fn with_action<F>(mut self, production: usize, action: F) -> LlkActions<T>
+    where
+        F: Fn(Vec<T>) -> T + 'static,
+    {
+        self.actions.insert(production, Box::new(action));
+        self
+    }
+
+    pub fn with_labeled_action<F>(mut self, label: &str, action: F) -> LlkActions<T>
+    where
+        F: Fn(Vec<T>) -> T + 'static,
+    {
+        self.labeled_actions
+            .insert(label.to_owned(), Box::new(action));
+        self
+    }
+}
+
+/*
+ * Listener evaluating the actions as non-terminal symbols complete.
+ * Values of the symbols derived so far are kept for every open non-terminal
+ */
+pub(super) struct LlkActionEvaluator<'a, T> {
+    grammar: &'a LlkGrammar,
+    terminal: &'a dyn Fn(char) -> T,
+    actions: HashMap<usize, &'a LlkAction<T>>,
+    open_values: Vec<(Option<usize>, Vec<T>)>,
+    value: Option<T>,
+    error: Option<LlkError>,
+}
+
+impl<'a, T> LlkActionEvaluator<'a, T> {
+    pub(super) fn new(
+        grammar: &'a LlkGrammar,
+        actions: &'a LlkActions<T>,
+    ) -> Result<LlkActionEvaluator<'a, T>, LlkError> {
+        let mut resolved_actions = HashMap::new();
+
+        for (production, action) in &actions.actions {
+            if *production >= grammar.productions.len() {
+                return Err(LlkError::IllegalOperation(format!(
+                    "semantic action for non-existent production {}",
+                    production
+                )));
+            }
+            resolved_actions.insert(*production, action);
+        }
+
+        for (label, action) in &actions.labeled_actions {
+            let production = grammar.labeled_production(label).ok_or_else(|| {
+                LlkError::IllegalOperation(format!(
+                    "semantic action for unknown production label \"{}\"",
+                    label
+                ))
+            })?;
+            resolved_actions.insert(production, action);
+        }
+
+        Ok(LlkActionEvaluator {
+            grammar,
+            terminal: actions.terminal.as_ref(),
+            actions: resolved_actions,
+            open_values: Vec::new(),
+            value: None,
+            error: None,
+        })
+    }
+
+    pub(super) fn into_value(self) -> Result<T, LlkError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        self.value.ok_or_else(|| {
+            LlkError::IllegalOperation("semantic actions produced no value".to_string())
+        })
+    }
+
+    fn push_value(&mut self, value: T) {
+        if let Some((_production, values)) = self.open_values.last_mut() {
+            values.push(value);
+        } else {
+            self.value = Some(value);
+        }
+    }
+}
+
+impl<T> LlkParseListener for LlkActionEvaluator<'_, T> {
+    fn enter_nonterminal(&mut self, _nterm: char, production: Option<usize>) {
+        self.open_values.push((production, Vec::new()));
+    }
+
+    fn exit_nonterminal(&mut self, nterm: char) {
+        let (production, mut values) = self
+            .open_values
+            .pop()
+            .expect("LlkActionEvaluator fatal error: exit without open non-terminal");
+
+        /* Values of the enclosing productions are incomplete after an error */
+        if self.error.is_some() {
+            return;
+        }
+
+        let value = match production.and_then(|production| self.actions.get(&production)) {
+            Some(action) => action(values),
+            None if values.len() == 1 => values.pop().unwrap(),
+            None => {
+                let production = production
+                    .map(|production| {
+                        LlkGrammar::format_production(&self.grammar.productions[production])
+                    })
+                    .unwrap_or_else(|| nterm.to_string());
+
+                self.error = Some(LlkError::IllegalOperation(format!(
+                    "no semantic action for production {}",
+                    production.escape_default()
+                )));
+                return;
+            }
+        };
+
+        self.push_value(value);
+    }
+
+    fn terminal(&mut self, symbol: char, _span: LlkSpan, _leading_trivia: &str) {
+        let value = (self.terminal)(symbol);
+        self.push_value(value);
+    }
+}
+
+#[test]
+fn semantic_actions_test() {
+    use crate::grammar::char_class::LlkCharClass;
+    use crate::grammar::expression_grammar;
+    use crate::grammar::parser::LlkParser;
+
+    /* Sum of digits with parentheses */
+    let grammar = expression_grammar()
+        .with_char_classes(
+            vec![('a', LlkCharClass::from_pattern("[0-9]").unwrap())]
+                .drain(..)
+                .collect(),
+        )
+        .unwrap()
+        .with_labels(vec![(4, "parens".to_string())].drain(..).collect())
+        .unwrap();
+
+    let parser = LlkParser::new(grammar);
+
+    let actions = LlkActions::new(|symbol| symbol.to_digit(10).unwrap_or_default() as i64)
+        .with_action(1, |values| values[0] + values[1])
+        .with_action(2, |values| values[1] + values[2])
+        .with_action(3, |_values| 0)
+        .with_labeled_action("parens", |values| values[1]);
+
+    assert_eq!(
+        parser.parse_with_actions("1+(2+3)+4", &actions).unwrap(),
+        10
+    );
+    assert_eq!(parser.parse_with_actions("((7))", &actions).unwrap(), 7);
+    assert!(matches!(
+        parser.parse_with_actions("1+", &actions),
+        Err(LlkError::UnexpectedEndOfInput { position: 2, .. })
+    ));
+
+    /* Values may be of any type, e.g. nodes of an abstract syntax tree */
+    let actions = LlkActions::new(|symbol| symbol.to_string())
+        .with_action(1, |values| {
+            if values[1].is_empty() {
+                values[0].clone()
+            } else {
+                format!("(+ {} {})", values[0], values[1])
+            }
+        })
+        .with_action(2, |values| {
+            if values[2].is_empty() {
+                values[1].clone()
+            } else {
+                format!("(+ {} {})", values[1], values[2])
+            }
+        })
+        .with_action(3, |_values| String::default())
+        .with_labeled_action("parens", |values| values[1].clone());
+
+    assert_eq!(
+        parser.parse_with_actions("1+(2+3)+4", &actions).unwrap(),
+        "(+ 1 (+ (+ 2 3) 4))"
+    );
+
+    /* Production with several RHS symbols needs an action */
+    let actions = LlkActions::new(|symbol| symbol.to_string());
+    assert!(matches!(
+        parser.parse_with_actions("1+2", &actions),
+        Err(LlkError::IllegalOperation(_))
+    ));
+
+    /* Actions of the enclosing productions are not run once one is missing */
+    let actions = LlkActions::new(|_symbol| 0)
+        .with_action(1, |values| values[0] + values[1])
+        .with_action(3, |_values| 0);
+    assert!(matches!(
+        parser.parse_with_actions("1+2", &actions),
+        Err(LlkError::IllegalOperation(_))
+    ));
+
+    let actions = LlkActions::new(|_symbol| 0).with_labeled_action("brackets", |_values| 0);
+    assert!(matches!(
+        parser.parse_with_actions("1", &actions),
+        Err(LlkError::IllegalOperation(_))
+    ));
+}
@@ -0,0 +1,653 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use super::tree::{LlkNodeId, LlkNodeKind, LlkTree};
+use super::LlkGrammar;
+use crate::error::LlkError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlkAttributeKind {
+    Inherited,
+    Synthesized,
+}
+
+type LlkEquationFn<V> = Box<dyn Fn(&[&V]) -> V>;
+type LlkTerminalAttributeFn<V> = Box<dyn Fn(char) -> V>;
+
+/* Attribute of a symbol at the given position of a production */
+type LlkAttributeRef = (usize, String);
+
+/* Attribute of a tree node */
+type LlkAttributeInstance = (LlkNodeId, String);
+
+/*
+ * Equation of a production defining one attribute from the others.
+ * Attributes are referred to by the position of their symbol in the
+ * production, 0 stands for the LHS and 1.. for the RHS symbols
+ */
+struct LlkEquation<V> {
+    production: usize,
+    target: LlkAttributeRef,
+    dependencies: Vec<LlkAttributeRef>,
+    function: LlkEquationFn<V>,
+}
+
+/* Dependency graph over the attributes of a production, or of a non-terminal symbol */
+type LlkDependencyGraph = BTreeSet<(LlkAttributeRef, LlkAttributeRef)>;
+
+/*
+ * Attribute grammar over the productions of a context-free grammar.
+ * Synthesized attributes of a non-terminal symbol are defined by every
+ * its production, inherited ones by every production having it in RHS.
+ * Terminal symbols have synthesized attributes computed from the input
+ */
+pub struct LlkAttributeGrammar<'a, V> {
+    grammar: &'a LlkGrammar,
+    attributes: HashMap<char, Vec<(String, LlkAttributeKind)>>,
+    terminal_attributes: Vec<(String, LlkTerminalAttributeFn<V>)>,
+    equations: Vec<LlkEquation<V>>,
+}
+
+impl<'a, V: Clone> LlkAttributeGrammar<'a, V> {
+    pub fn new(grammar: &'a LlkGrammar) -> LlkAttributeGrammar<'a, V> {
+        LlkAttributeGrammar {
+            grammar,
+            attributes: HashMap::new(),
+            terminal_attributes: Vec::new(),
+            equations: Vec::new(),
+        }
+    }
+
+    pub fn with_inherited(mut self, nterm: char, name: &str) -> LlkAttributeGrammar<'a, V> {
+        self.attributes
+            .entry(nterm)
+            .or_default()
+            .push((name.to_owned(), LlkAttributeKind::Inherited));
+        self
+    }
+
+    pub fn with_synthesized(mut self, nterm: char, name: &str) -> LlkAttributeGrammar<'a, V> {
+        self.attributes
+            .entry(nterm)
+            .or_default()
+            .push((name.to_owned(), LlkAttributeKind::Synthesized));
+        self
+    }
+
+    pub fn with_terminal_attribute<F>(
+        mut self,
+        name: &str,
+        function: F,
+    ) -> LlkAttributeGrammar<'a, V>
+    where
+        F: Fn(char) -> V + 'static,
+    {
+        self.terminal_attributes
+            .push((name.to_owned(), Box::new(function)));
+        self
+    }
+
+    pub fn with_equation<F>(
+        mut self,
+        production: usize,
+        target: (usize, &str),
+        dependencies: &[(usize, &str)],
+        function: F,
+    ) -> LlkAttributeGrammar<'a, V>
+    where
+        F: Fn(&[&V]) -> V + 'static,
+    {
+        self.equations.push(LlkEquation {
+            production,
+            target: (target.0, target.1.to_owned()),
+            dependencies: dependencies
+                .iter()
+                .map(|(position, name)| (*position, name.to_string()))
+                .collect(),
+            function: Box::new(function),
+        });
+        self
+    }
+
+    /*
+     * Check that every attribute is defined exactly once in every production
+     * and that no derivation tree can have circular attribute dependencies
+     */
+    pub fn validate(&self) -> Result<(), LlkError> {
+        for equation in &self.equations {
+            self.assert_equation(equation)?;
+        }
+
+        for (production, (nterm, _derivative)) in self.grammar.productions.iter().enumerate() {
+            let symbols = self.get_production_symbols(production);
+
+            let mut required: Vec<LlkAttributeRef> = self
+                .get_attributes(*nterm, LlkAttributeKind::Synthesized)
+                .map(|name| (0, name.clone()))
+                .collect();
+            for (position, symbol) in symbols.iter().enumerate().skip(1) {
+                required.extend(
+                    self.get_attributes(*symbol, LlkAttributeKind::Inherited)
+                        .map(|name| (position, name.clone())),
+                );
+            }
+
+            for attribute in required {
+                let definitions = self
+                    .get_equations(production)
+                    .filter(|equation| equation.target == attribute)
+                    .count();
+
+                if definitions != 1 {
+                    return Err(LlkError::InvalidGrammar(format!(
+                        "attribute \"{}\" of symbol {} is defined {} times in production {}",
+                        attribute.1,
+                        attribute.0,
+                        definitions,
+                        LlkGrammar::format_production(&self.grammar.productions[production])
+                    )));
+                }
+            }
+        }
+
+        self.assert_noncircular()
+    }
+
+    /*
+     * Evaluate attributes of a finished tree in the order of their dependencies.
+     * Inherited attributes of the root are given, synthesized ones are returned
+     */
+    pub fn evaluate(
+        &self,
+        tree: &LlkTree,
+        root_attributes: HashMap<String, V>,
+    ) -> Result<HashMap<String, V>, LlkError> {
+        if tree.has_errors() {
+            return Err(LlkError::IllegalOperation(
+                "evaluating attributes of a tree with errors".to_string(),
+            ));
+        }
+
+        /* Attribute references index into the children of every matching node */
+        for equation in &self.equations {
+            self.assert_equation(equation)?;
+        }
+
+        /* Instances of attributes with the equations defining them */
+        let mut values: HashMap<LlkAttributeInstance, V> = HashMap::new();
+        let mut instances: Vec<(
+            LlkAttributeInstance,
+            &LlkEquation<V>,
+            Vec<LlkAttributeInstance>,
+        )> = Vec::new();
+
+        for (name, value) in root_attributes {
+            values.insert((tree.root(), name), value);
+        }
+
+        for id in tree.node_ids() {
+            let node = tree.node(id);
+
+            if let LlkNodeKind::Terminal { symbol, .. } = node.kind() {
+                for (name, function) in &self.terminal_attributes {
+                    values.insert((id, name.clone()), function(*symbol));
+                }
+            }
+
+            if !node.is_nonterminal() {
+                continue;
+            }
+
+            /* Position 0 is the node itself, the rest are its children */
+            let children = get_node_children(tree, id);
+            let instance = |(position, name): &LlkAttributeRef| {
+                let node = if *position == 0 {
+                    id
+                } else {
+                    children[*position - 1]
+                };
+                (node, name.clone())
+            };
+
+            for equation in self.get_equations(self.get_node_production(tree, id)?) {
+                instances.push((
+                    instance(&equation.target),
+                    equation,
+                    equation.dependencies.iter().map(instance).collect(),
+                ));
+            }
+        }
+
+        /* Order instances topologically, starting with those depending on known values only */
+        let mut missing: Vec<usize> = vec![0; instances.len()];
+        let mut dependents: HashMap<&LlkAttributeInstance, Vec<usize>> = HashMap::new();
+
+        for (i, (_target, _equation, dependencies)) in instances.iter().enumerate() {
+            for dependency in dependencies {
+                if !values.contains_key(dependency) {
+                    missing[i] += 1;
+                    dependents.entry(dependency).or_default().push(i);
+                }
+            }
+        }
+
+        let mut ready: Vec<usize> = (0..instances.len()).filter(|i| missing[*i] == 0).collect();
+        let mut evaluated = 0;
+
+        while let Some(i) = ready.pop() {
+            let (target, equation, dependencies) = &instances[i];
+            let arguments: Vec<&V> = dependencies.iter().map(|dep| &values[dep]).collect();
+            let value = (equation.function)(&arguments);
+            values.insert(target.clone(), value);
+            evaluated += 1;
+
+            for dependent in dependents.remove(target).into_iter().flatten() {
+                missing[dependent] -= 1;
+                if missing[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+        }
+
+        if evaluated < instances.len() {
+            let i = (0..instances.len()).find(|i| missing[*i] > 0).unwrap();
+            let ((node, name), _, _) = &instances[i];
+            return Err(LlkError::IllegalOperation(format!(
+                "attribute \"{}\" of symbol '{}' depends on itself or on an undefined attribute",
+                name,
+                tree.symbol(*node).unwrap_or_default().escape_default()
+            )));
+        }
+
+        let root_symbol = tree.symbol(tree.root()).unwrap_or_default();
+        Ok(self
+            .get_attributes(root_symbol, LlkAttributeKind::Synthesized)
+            .filter_map(|name| {
+                values
+                    .get(&(tree.root(), name.clone()))
+                    .map(|value| (name.clone(), value.clone()))
+            })
+            .collect())
+    }
+}
+
+impl<V> LlkAttributeGrammar<'_, V> {
+    fn get_attributes(
+        &self,
+        symbol: char,
+        kind: LlkAttributeKind,
+    ) -> impl Iterator<Item = &String> {
+        self.attributes
+            .get(&symbol)
+            .into_iter()
+            .flatten()
+            .filter(move |(_name, attribute_kind)| *attribute_kind == kind)
+            .map(|(name, _kind)| name)
+    }
+
+    fn get_equations(&self, production: usize) -> impl Iterator<Item = &LlkEquation<V>> {
+        self.equations
+            .iter()
+            .filter(move |equation| equation.production == production)
+    }
+
+    /* LHS followed by RHS symbols */
+    fn get_production_symbols(&self, production: usize) -> Vec<char> {
+        let (nterm, derivative) = &self.grammar.productions[production];
+
+        std::iter::once(*nterm)
+            .chain(derivative.iter().flat_map(|derivative| derivative.chars()))
+            .collect()
+    }
+
+    fn has_attribute(&self, symbol: char, name: &str) -> bool {
+        if self.grammar.is_term(symbol) {
+            self.terminal_attributes
+                .iter()
+                .any(|(attribute_name, _)| attribute_name == name)
+        } else {
+            self.attributes
+                .get(&symbol)
+                .is_some_and(|attributes| attributes.iter().any(|(attr, _)| attr == name))
+        }
+    }
+
+    fn assert_equation(&self, equation: &LlkEquation<V>) -> Result<(), LlkError> {
+        if equation.production >= self.grammar.productions.len() {
+            return Err(LlkError::InvalidGrammar(format!(
+                "attribute equation for non-existent production {}",
+                equation.production
+            )));
+        }
+
+        let symbols = self.get_production_symbols(equation.production);
+        let production =
+            LlkGrammar::format_production(&self.grammar.productions[equation.production]);
+
+        /* LHS defines its synthesized attributes, RHS symbols get their inherited ones */
+        let (position, name) = &equation.target;
+        let target_kind = if *position == 0 {
+            LlkAttributeKind::Synthesized
+        } else {
+            LlkAttributeKind::Inherited
+        };
+        let defined = symbols.get(*position).is_some_and(|symbol| {
+            self.get_attributes(*symbol, target_kind)
+                .any(|attribute| attribute == name)
+        });
+
+        if !defined {
+            return Err(LlkError::InvalidGrammar(format!(
+                "production {} can not define attribute \"{}\" of symbol {}",
+                production.escape_default(),
+                name,
+                position
+            )));
+        }
+
+        for (position, name) in &equation.dependencies {
+            if !symbols
+                .get(*position)
+                .is_some_and(|symbol| self.has_attribute(*symbol, name))
+            {
+                return Err(LlkError::InvalidGrammar(format!(
+                    "production {} refers to unknown attribute \"{}\" of symbol {}",
+                    production.escape_default(),
+                    name,
+                    position
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /*
+     * Knuth's circularity test. For every non-terminal symbol collect the
+     * possible dependencies of its synthesized attributes on the inherited
+     * ones over all the subtrees it may derive, and check that no production
+     * closes a cycle when combined with any of those of its RHS symbols
+     */
+    fn assert_noncircular(&self) -> Result<(), LlkError> {
+        let mut io_graphs: HashMap<char, HashSet<LlkDependencyGraph>> = HashMap::new();
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for production in 0..self.grammar.productions.len() {
+                let symbols = self.get_production_symbols(production);
+                let rhs_nterms: Vec<(usize, char)> = symbols
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .skip(1)
+                    .filter(|(_position, symbol)| self.grammar.is_nterm(*symbol))
+                    .collect();
+
+                /* Subtrees of some RHS symbol are not known yet */
+                if rhs_nterms
+                    .iter()
+                    .any(|(_position, symbol)| !io_graphs.contains_key(symbol))
+                {
+                    continue;
+                }
+
+                let mut local_graph = LlkDependencyGraph::new();
+                for equation in self.get_equations(production) {
+                    for dependency in &equation.dependencies {
+                        local_graph.insert((dependency.clone(), equation.target.clone()));
+                    }
+                }
+
+                /* Try every combination of the RHS symbol graphs */
+                let choices: Vec<Vec<&LlkDependencyGraph>> = rhs_nterms
+                    .iter()
+                    .map(|(_position, symbol)| io_graphs[symbol].iter().collect())
+                    .collect();
+                let mut combination = vec![0; choices.len()];
+                let mut new_graphs = Vec::new();
+
+                loop {
+                    let mut graph = local_graph.clone();
+                    for (i, (position, _symbol)) in rhs_nterms.iter().enumerate() {
+                        graph.extend(choices[i][combination[i]].iter().map(|(from, to)| {
+                            ((*position, from.1.clone()), (*position, to.1.clone()))
+                        }));
+                    }
+
+                    let closure = get_transitive_closure(&graph);
+                    if let Some(((position, name), _)) =
+                        closure.iter().find(|(from, to)| from == to)
+                    {
+                        return Err(LlkError::InvalidGrammar(format!(
+                            "circular dependency of attribute \"{}\" of symbol {} in production {}",
+                            name,
+                            position,
+                            LlkGrammar::format_production(&self.grammar.productions[production])
+                                .escape_default()
+                        )));
+                    }
+
+                    /* Project onto the dependencies between LHS attributes */
+                    new_graphs.push(
+                        closure
+                            .into_iter()
+                            .filter(|((from, _), (to, _))| *from == 0 && *to == 0)
+                            .collect::<LlkDependencyGraph>(),
+                    );
+
+                    /* Next combination, like incrementing a mixed radix number */
+                    let mut i = 0;
+                    while i < combination.len() {
+                        combination[i] += 1;
+                        if combination[i] < choices[i].len() {
+                            break;
+                        }
+                        combination[i] = 0;
+                        i += 1;
+                    }
+                    if i == combination.len() {
+                        break;
+                    }
+                }
+
+                let lhs_graphs = io_graphs.entry(symbols[0]).or_default();
+                for graph in new_graphs {
+                    changed |= lhs_graphs.insert(graph);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /* Production recorded on a tree node, checked against the node children */
+    fn get_node_production(&self, tree: &LlkTree, id: LlkNodeId) -> Result<usize, LlkError> {
+        let nterm = tree.symbol(id).unwrap_or_default();
+
+        tree.production(id)
+            .filter(|production| *production < self.grammar.productions.len())
+            .filter(|production| {
+                let symbols = self.get_production_symbols(*production);
+                symbols[0] == nterm && symbols.len() == get_node_children(tree, id).len() + 1
+            })
+            .ok_or_else(|| {
+                LlkError::IllegalOperation(format!(
+                    "tree node '{}' has no matching production of the grammar",
+                    nterm.escape_default()
+                ))
+            })
+    }
+}
+
+/* Children standing for the production RHS symbols, explicit ε nodes and end marker excluded */
+fn get_node_children(tree: &LlkTree, id: LlkNodeId) -> Vec<LlkNodeId> {
+    tree.children(id)
+        .iter()
+        .copied()
+        .filter(|child| !tree.node(*child).is_epsilon() && !tree.node(*child).is_eof())
+        .collect()
+}
+
+fn get_transitive_closure(graph: &LlkDependencyGraph) -> LlkDependencyGraph {
+    let mut closure = graph.clone();
+    let mut changed = true;
+
+    while changed {
+        let new_edges: Vec<_> = closure
+            .iter()
+            .flat_map(|(from, middle)| {
+                closure
+                    .range((middle.clone(), (0, String::default()))..)
+                    .take_while(move |(next_from, _)| next_from == middle)
+                    .map(move |(_, to)| (from.clone(), to.clone()))
+            })
+            .filter(|edge| !closure.contains(edge))
+            .collect();
+
+        changed = !new_edges.is_empty();
+        closure.extend(new_edges);
+    }
+
+    closure
+}
+
+#[test]
+fn attribute_grammar_test() {
+    use crate::grammar::parser::LlkParser;
+
+    /* Binary numerals, value so far is inherited by the rest of the digits */
+    fn create_grammar() -> LlkGrammar {
+        let term_symbols = vec!['0', '1'].drain(..).collect();
+        let nterm_symbols = vec!['S', 'B', 'R'].drain(..).collect();
+        let start_symbol = 'S';
+        let lookahead = 1;
+        let productions = vec![
+            ('S', Some("BR".to_string())),
+            ('R', Some("BR".to_string())),
+            ('R', None),
+            ('B', Some("0".to_string())),
+            ('B', Some("1".to_string())),
+        ];
+
+        LlkGrammar::new(
+            term_symbols,
+            nterm_symbols,
+            start_symbol,
+            lookahead,
+            productions,
+        )
+        .unwrap()
+    }
+
+    let grammar = create_grammar();
+    let attribute_grammar = || {
+        LlkAttributeGrammar::<u64>::new(&grammar)
+            .with_synthesized('S', "value")
+            .with_inherited('R', "left")
+            .with_synthesized('R', "value")
+            .with_synthesized('B', "bit")
+            .with_terminal_attribute("digit", |symbol| symbol.to_digit(2).unwrap() as u64)
+            .with_equation(0, (2, "left"), &[(1, "bit")], |args| *args[0])
+            .with_equation(0, (0, "value"), &[(2, "value")], |args| *args[0])
+            .with_equation(1, (2, "left"), &[(0, "left"), (1, "bit")], |args| {
+                args[0] * 2 + args[1]
+            })
+            .with_equation(1, (0, "value"), &[(2, "value")], |args| *args[0])
+            .with_equation(2, (0, "value"), &[(0, "left")], |args| *args[0])
+            .with_equation(3, (0, "bit"), &[(1, "digit")], |args| *args[0])
+            .with_equation(4, (0, "bit"), &[(1, "digit")], |args| *args[0])
+    };
+
+    let parser = LlkParser::new(create_grammar());
+    let tree = parser.parse("1101").unwrap();
+
+    let attributes = attribute_grammar();
+    attributes.validate().unwrap();
+    let values = attributes.evaluate(&tree, HashMap::new()).unwrap();
+    assert_eq!(values["value"], 13);
+
+    let tree = LlkParser::new(create_grammar())
+        .with_explicit_nodes()
+        .parse("1101")
+        .unwrap();
+    let values = attributes.evaluate(&tree, HashMap::new()).unwrap();
+    assert_eq!(values["value"], 13);
+
+    let tree = parser.parse("0").unwrap();
+    let values = attributes.evaluate(&tree, HashMap::new()).unwrap();
+    assert_eq!(values["value"], 0);
+
+    /* Inherited attribute without definition */
+    let attributes = LlkAttributeGrammar::<u64>::new(&grammar)
+        .with_inherited('R', "left")
+        .with_synthesized('R', "value")
+        .with_equation(2, (0, "value"), &[(0, "left")], |args| *args[0]);
+    assert!(matches!(
+        attributes.validate(),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    /* Inherited attribute defined twice */
+    let attributes =
+        attribute_grammar().with_equation(0, (2, "left"), &[(1, "bit")], |args| *args[0]);
+    assert!(matches!(
+        attributes.validate(),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    /* Attribute of an undeclared symbol */
+    let attributes = attribute_grammar().with_equation(3, (1, "bit"), &[], |_args| 0);
+    assert!(matches!(
+        attributes.validate(),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    /* Dependency on a symbol beyond the right-hand side */
+    let attributes = LlkAttributeGrammar::<u64>::new(&grammar)
+        .with_synthesized('B', "bit")
+        .with_terminal_attribute("digit", |symbol| symbol.to_digit(2).unwrap() as u64)
+        .with_equation(3, (0, "bit"), &[(1, "digit")], |args| *args[0])
+        .with_equation(4, (0, "bit"), &[(3, "digit")], |args| *args[0]);
+    assert!(matches!(
+        attributes.evaluate(&parser.parse("1101").unwrap(), HashMap::new()),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    /* Cycle within a single production */
+    let attributes = LlkAttributeGrammar::<u64>::new(&grammar)
+        .with_synthesized('B', "bit")
+        .with_synthesized('B', "parity")
+        .with_equation(3, (0, "bit"), &[(0, "parity")], |args| *args[0])
+        .with_equation(3, (0, "parity"), &[(0, "bit")], |args| *args[0])
+        .with_equation(4, (0, "bit"), &[], |_args| 1)
+        .with_equation(4, (0, "parity"), &[], |_args| 1);
+    assert!(matches!(
+        attributes.validate(),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    /* Inherited attribute of R depends on its value, which depends on the inherited one below */
+    let attributes = LlkAttributeGrammar::<u64>::new(&grammar)
+        .with_synthesized('S', "value")
+        .with_inherited('R', "left")
+        .with_synthesized('R', "value")
+        .with_synthesized('B', "bit")
+        .with_equation(0, (2, "left"), &[(2, "value")], |args| *args[0])
+        .with_equation(0, (0, "value"), &[(2, "value")], |args| *args[0])
+        .with_equation(1, (2, "left"), &[(0, "left")], |args| *args[0])
+        .with_equation(1, (0, "value"), &[(2, "value")], |args| *args[0])
+        .with_equation(2, (0, "value"), &[(0, "left")], |args| *args[0])
+        .with_equation(3, (0, "bit"), &[], |_args| 0)
+        .with_equation(4, (0, "bit"), &[], |_args| 1);
+    assert!(matches!(
+        attributes.validate(),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+    assert!(matches!(
+        attributes.evaluate(&tree, HashMap::new()),
+        Err(LlkError::IllegalOperation(_))
+    ));
+}
@@ -0,0 +1,280 @@
+use std::fmt;
+
+use crate::error::LlkError;
+
+/*
+ * Set of characters matched by a single terminal symbol.
+ * Class never contains the end of input marker
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LlkCharClass {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl LlkCharClass {
+    const ANY_PATTERN: &'static str = ".";
+
+    pub fn new(ranges: Vec<(char, char)>, negated: bool) -> LlkCharClass {
+        LlkCharClass {
+            ranges: normalize_ranges(ranges),
+            negated,
+        }
+    }
+
+    pub fn any() -> LlkCharClass {
+        LlkCharClass::new(Vec::new(), true)
+    }
+
+    pub fn from_pattern(pattern: &str) -> Result<LlkCharClass, LlkError> {
+        if pattern == LlkCharClass::ANY_PATTERN {
+            return Ok(LlkCharClass::any());
+        }
+
+        let invalid_pattern =
+            || LlkError::InvalidGrammar(format!("invalid character class: \"{}\"", pattern));
+
+        let body = pattern
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or_else(invalid_pattern)?;
+
+        let (negated, body) = if let Some(body) = body.strip_prefix('^') {
+            (true, body)
+        } else {
+            (false, body)
+        };
+
+        /* Resolve escape sequences, remember which symbols were escaped */
+        let mut symbols = Vec::new();
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                symbols.push((chars.next().ok_or_else(invalid_pattern)?, true));
+            } else {
+                symbols.push((c, false));
+            }
+        }
+
+        if symbols.is_empty() {
+            return Err(invalid_pattern());
+        }
+
+        /* Collect single symbols and a-b ranges */
+        let mut ranges = Vec::new();
+        let mut i = 0;
+        while i < symbols.len() {
+            let (start, _) = symbols[i];
+
+            if i + 2 < symbols.len() && symbols[i + 1] == ('-', false) {
+                let (end, _) = symbols[i + 2];
+                if end < start {
+                    return Err(invalid_pattern());
+                }
+                ranges.push((start, end));
+                i += 3;
+            } else {
+                ranges.push((start, start));
+                i += 1;
+            }
+        }
+
+        Ok(LlkCharClass::new(ranges, negated))
+    }
+
+    pub fn contains(&self, symbol: char) -> bool {
+        symbol != super::LlkGrammar::EOF
+            && self
+                .ranges
+                .iter()
+                .any(|(start, end)| *start <= symbol && symbol <= *end)
+                != self.negated
+    }
+
+    pub fn case_insensitive(&self) -> LlkCharClass {
+        /* No symbol after the last Adlam letter has case mappings */
+        const LAST_CASED_SYMBOL: char = '\u{1E943}';
+
+        let mut ranges = self.ranges.clone();
+
+        for (start, end) in &self.ranges {
+            for symbol in *start..=std::cmp::min(*end, LAST_CASED_SYMBOL) {
+                for variant in symbol.to_lowercase().chain(symbol.to_uppercase()) {
+                    if variant < *start || *end < variant {
+                        ranges.push((variant, variant));
+                    }
+                }
+            }
+        }
+
+        LlkCharClass::new(ranges, self.negated)
+    }
+
+    pub fn intersects(&self, other: &LlkCharClass) -> bool {
+        let ranges_a = self.get_positive_ranges();
+        let ranges_b = other.get_positive_ranges();
+
+        ranges_a.iter().any(|(start_a, end_a)| {
+            ranges_b
+                .iter()
+                .any(|(start_b, end_b)| start_a <= end_b && start_b <= end_a)
+        })
+    }
+}
+
+impl LlkCharClass {
+    fn get_positive_ranges(&self) -> Vec<(u32, u32)> {
+        /* End of input marker is excluded from every class */
+        let first = super::LlkGrammar::EOF as u32 + 1;
+        let last = char::MAX as u32;
+
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|(start, end)| (std::cmp::max(*start as u32, first), *end as u32))
+            .filter(|(start, end)| start <= end);
+
+        if !self.negated {
+            return ranges.collect();
+        }
+
+        /* Complement sorted disjoint ranges */
+        let mut complement = Vec::new();
+        let mut next_start = first;
+        for (start, end) in ranges {
+            if start > next_start {
+                complement.push((next_start, start - 1));
+            }
+            next_start = end + 1;
+        }
+        if next_start <= last {
+            complement.push((next_start, last));
+        }
+
+        complement
+    }
+}
+
+impl fmt::Display for LlkCharClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if self.negated && self.ranges.is_empty() {
+            return write!(f, "{}", LlkCharClass::ANY_PATTERN);
+        }
+
+        let escape = |c: char| {
+            if matches!(c, '\\' | ']' | '-' | '^') {
+                format!("\\{}", c)
+            } else {
+                c.to_string()
+            }
+        };
+
+        write!(f, "[{}", if self.negated { "^" } else { "" })?;
+        for (start, end) in &self.ranges {
+            if start == end {
+                write!(f, "{}", escape(*start))?;
+            } else {
+                write!(f, "{}-{}", escape(*start), escape(*end))?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+fn normalize_ranges(mut ranges: Vec<(char, char)>) -> Vec<(char, char)> {
+    /* Sort ranges and merge overlapping or adjacent ones */
+    ranges.retain(|(start, end)| start <= end);
+    ranges.sort();
+
+    let mut normalized: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        if let Some(last) = normalized.last_mut() {
+            if start as u32 <= last.1 as u32 + 1 {
+                last.1 = std::cmp::max(last.1, end);
+                continue;
+            }
+        }
+        normalized.push((start, end));
+    }
+
+    normalized
+}
+
+#[test]
+fn char_class_pattern_test() {
+    let digits = LlkCharClass::from_pattern("[0-9]").unwrap();
+    assert!(digits.contains('0') && digits.contains('9'));
+    assert!(!digits.contains('a'));
+
+    let ident = LlkCharClass::from_pattern("[a-zA-Z_]").unwrap();
+    assert!(ident.contains('q') && ident.contains('Q') && ident.contains('_'));
+    assert!(!ident.contains('-'));
+
+    let not_quote = LlkCharClass::from_pattern("[^\"\\\\]").unwrap();
+    assert!(not_quote.contains('a') && not_quote.contains('я'));
+    assert!(!not_quote.contains('"') && !not_quote.contains('\\'));
+    assert!(!not_quote.contains(super::LlkGrammar::EOF));
+
+    let any = LlkCharClass::from_pattern(".").unwrap();
+    assert!(any.contains('x') && any.contains('∑'));
+    assert!(!any.contains(super::LlkGrammar::EOF));
+
+    let escaped = LlkCharClass::from_pattern("[\\-\\]a-c]").unwrap();
+    assert!(escaped.contains('-') && escaped.contains(']') && escaped.contains('b'));
+
+    assert_eq!(digits.to_string(), "[0-9]");
+    assert_eq!(any.to_string(), ".");
+    assert_eq!(
+        LlkCharClass::from_pattern("[^a-cb-f_]")
+            .unwrap()
+            .to_string(),
+        "[^_a-f]"
+    );
+
+    assert!(LlkCharClass::from_pattern("0-9").is_err());
+    assert!(LlkCharClass::from_pattern("[]").is_err());
+    assert!(LlkCharClass::from_pattern("[9-0]").is_err());
+}
+
+#[test]
+fn char_class_case_insensitive_test() {
+    let letters = LlkCharClass::from_pattern("[a-fя]")
+        .unwrap()
+        .case_insensitive();
+    assert!(letters.contains('c') && letters.contains('C'));
+    assert!(letters.contains('я') && letters.contains('Я'));
+    assert!(!letters.contains('G'));
+
+    let not_letters = LlkCharClass::from_pattern("[^a-z]")
+        .unwrap()
+        .case_insensitive();
+    assert!(!not_letters.contains('q') && !not_letters.contains('Q'));
+    assert!(not_letters.contains('1'));
+
+    /* Every range is folded, however long it is */
+    let wide = LlkCharClass::from_pattern("[a-\u{FFFF}]")
+        .unwrap()
+        .case_insensitive();
+    assert!(wide.contains('A') && wide.contains('Z'));
+    assert!(!wide.contains('@'));
+
+    let adlam = LlkCharClass::from_pattern("[\u{1E922}-\u{10FFFF}]")
+        .unwrap()
+        .case_insensitive();
+    assert!(adlam.contains('\u{1E900}'));
+}
+
+#[test]
+fn char_class_intersection_test() {
+    let digits = LlkCharClass::from_pattern("[0-9]").unwrap();
+    let letters = LlkCharClass::from_pattern("[a-z]").unwrap();
+    let hex = LlkCharClass::from_pattern("[0-9a-f]").unwrap();
+    let not_digits = LlkCharClass::from_pattern("[^0-9]").unwrap();
+
+    assert!(!digits.intersects(&letters));
+    assert!(digits.intersects(&hex) && letters.intersects(&hex));
+    assert!(!digits.intersects(&not_digits));
+    assert!(letters.intersects(&not_digits));
+    assert!(LlkCharClass::any().intersects(&digits));
+    assert!(LlkCharClass::any().intersects(&not_digits));
+}
@@ -0,0 +1,61 @@
+/*
+ * Symbols which may legally follow an incomplete input.
+ * Lookahead strings are the k-length strings the parser may meet next,
+ * with the already typed symbols stripped off
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LlkCompletion {
+    terminals: Vec<char>,
+    lookaheads: Vec<String>,
+    open_nterms: Vec<char>,
+    viable: bool,
+}
+
+impl LlkCompletion {
+    pub(super) fn new(
+        mut lookaheads: Vec<String>,
+        open_nterms: Vec<char>,
+        viable: bool,
+    ) -> LlkCompletion {
+        lookaheads.sort();
+        lookaheads.dedup();
+
+        /* End of input is not a symbol to suggest */
+        let mut terminals: Vec<char> = lookaheads
+            .iter()
+            .filter_map(|lookahead| lookahead.chars().next())
+            .filter(|symbol| *symbol != super::LlkGrammar::EOF)
+            .collect();
+        terminals.dedup();
+
+        LlkCompletion {
+            terminals,
+            lookaheads,
+            open_nterms,
+            viable,
+        }
+    }
+
+    pub fn next_terminals(&self) -> &[char] {
+        &self.terminals
+    }
+
+    pub fn next_lookaheads(&self) -> &[String] {
+        &self.lookaheads
+    }
+
+    /* Non-terminal symbols being derived at the end of input, outermost first, the predicted one last */
+    pub fn open_nterms(&self) -> &[char] {
+        &self.open_nterms
+    }
+
+    pub fn is_viable_prefix(&self) -> bool {
+        self.viable
+    }
+
+    pub fn can_end(&self) -> bool {
+        self.lookaheads
+            .iter()
+            .any(|lookahead| lookahead.starts_with(super::LlkGrammar::EOF))
+    }
+}
@@ -0,0 +1,143 @@
+use super::LlkGrammar;
+
+/* Graphviz DOT rendering of grammars */
+
+impl LlkGrammar {
+    /*
+     * Non-terminal symbols depending on each other, an edge leads from
+     * the LHS of a production to every non-terminal symbol of its RHS and
+     * is labeled by the production. Start symbol has a double border
+     */
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec!["digraph grammar {".to_string()];
+
+        /* Start symbol first, then in the order of productions, then the rest sorted */
+        let mut unused_nterms: Vec<char> = self.nterm_symbols.iter().copied().collect();
+        unused_nterms.sort();
+
+        let mut nterms: Vec<char> = Vec::new();
+        for nterm in std::iter::once(self.start_symbol)
+            .chain(self.productions.iter().map(|(nterm, _derivative)| *nterm))
+            .chain(unused_nterms)
+        {
+            if !nterms.contains(&nterm) {
+                nterms.push(nterm);
+            }
+        }
+
+        for nterm in nterms {
+            let peripheries = if nterm == self.start_symbol {
+                ", peripheries=2"
+            } else {
+                ""
+            };
+            lines.push(format!(
+                "    \"{}\" [shape=ellipse{}];",
+                escape(&nterm.to_string()),
+                peripheries
+            ));
+        }
+
+        for (index, (nterm, derivative)) in self.productions.iter().enumerate() {
+            let derivative = derivative.as_deref().unwrap_or_default();
+            let mut rhs_nterms: Vec<char> = Vec::new();
+
+            for symbol in derivative.chars().filter(|symbol| self.is_nterm(*symbol)) {
+                if !rhs_nterms.contains(&symbol) {
+                    rhs_nterms.push(symbol);
+                }
+            }
+
+            let label = match self.production_label(index) {
+                Some(label) => format!("{} {}: ", index, label),
+                None => format!("{}: ", index),
+            };
+            let label = format!(
+                "{}{} → {}",
+                label,
+                nterm,
+                if derivative.is_empty() {
+                    "ε"
+                } else {
+                    derivative
+                }
+            );
+
+            for rhs_nterm in rhs_nterms {
+                lines.push(format!(
+                    "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                    escape(&nterm.to_string()),
+                    escape(&rhs_nterm.to_string()),
+                    escape(&label)
+                ));
+            }
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
+/* Text inside a quoted DOT string, quotes and non-printable characters are escaped */
+pub(super) fn escape(text: &str) -> String {
+    text.chars()
+        .map(|symbol| match symbol {
+            '\\' | '"' => symbol.escape_default().to_string(),
+            symbol if symbol.is_control() => symbol.escape_default().to_string(),
+            symbol => symbol.to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn dot_test() {
+    let create_grammar = || {
+        let term_symbols = vec!['a', '+', '(', ')', '"'].drain(..).collect();
+        let nterm_symbols = vec!['S', 'E', 'R', 'T'].drain(..).collect();
+        let start_symbol = 'S';
+        let lookahead = 1;
+        let productions = vec![
+            ('S', Some("E".to_string())),
+            ('E', Some("TR".to_string())),
+            ('R', Some("+TR".to_string())),
+            ('R', None),
+            ('T', Some("(E)".to_string())),
+            ('T', Some("a".to_string())),
+            ('T', Some("\"".to_string())),
+        ];
+
+        LlkGrammar::new(
+            term_symbols,
+            nterm_symbols,
+            start_symbol,
+            lookahead,
+            productions,
+        )
+        .unwrap()
+        .with_labels(vec![(4, "parens".to_string())].drain(..).collect())
+        .unwrap()
+    };
+
+    /* Productions without non-terminal symbols in RHS have no edges */
+    assert_eq!(
+        create_grammar().to_dot(),
+        [
+            "digraph grammar {",
+            "    \"S\" [shape=ellipse, peripheries=2];",
+            "    \"E\" [shape=ellipse];",
+            "    \"R\" [shape=ellipse];",
+            "    \"T\" [shape=ellipse];",
+            "    \"S\" -> \"E\" [label=\"0: S → E\"];",
+            "    \"E\" -> \"T\" [label=\"1: E → TR\"];",
+            "    \"E\" -> \"R\" [label=\"1: E → TR\"];",
+            "    \"R\" -> \"T\" [label=\"2: R → +TR\"];",
+            "    \"R\" -> \"R\" [label=\"2: R → +TR\"];",
+            "    \"T\" -> \"E\" [label=\"4 parens: T → (E)\"];",
+            "}",
+        ]
+        .join("\n")
+    );
+
+    /* Control characters never end up raw in the output */
+    assert_eq!(escape("\0\t\r\n\\\"ε"), "\\u{0}\\t\\r\\n\\\\\\\"ε");
+}
@@ -1,6 +1,9 @@
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use std::collections::HashSet;
 
+use super::char_class::LlkCharClass;
+use super::skip_rule::LlkSkipRule;
 use super::LlkGrammar;
 use crate::error::LlkError;
 
@@ -11,6 +14,12 @@ const LOOKAHEAD_KEY: &str = "lookahead";
 const PRODUCTIONS_KEY: &str = "productions";
 const PRODUCTION_NTERM_KEY: &str = "nterm";
 const PRODUCTION_DERIVATIVE_KEY: &str = "derivative";
+const PRODUCTION_LABEL_KEY: &str = "label";
+const CHAR_CLASSES_KEY: &str = "char_classes";
+const SKIP_KEY: &str = "skip";
+const CASE_INSENSITIVE_KEY: &str = "case_insensitive";
+const SKIP_OPEN_KEY: &str = "open";
+const SKIP_CLOSE_KEY: &str = "close";
 
 pub(super) fn parse_grammar_json(json_string: &str) -> Result<LlkGrammar, LlkError> {
     if let Ok(json_values) = serde_json::from_str(json_string) {
@@ -42,12 +51,12 @@ pub(super) fn parse_grammar_json(json_string: &str) -> Result<LlkGrammar, LlkErr
             ));
         }
         let start_symbol = start_symbol.as_str().unwrap();
-        if start_symbol.len() != 1 {
+        if start_symbol.chars().count() != 1 {
             return Err(LlkError::GrammarFromJsonFailed(
                 "invalid start symbol definition".to_string(),
             ));
         }
-        let start_symbol = start_symbol.chars().nth(0).unwrap();
+        let start_symbol = start_symbol.chars().next().unwrap();
 
         /* Lookahead */
         let lookahead = &json_values[LOOKAHEAD_KEY];
@@ -67,6 +76,7 @@ pub(super) fn parse_grammar_json(json_string: &str) -> Result<LlkGrammar, LlkErr
         }
         let productions_array = productions.as_array().unwrap();
         let mut productions = Vec::new();
+        let mut labels = HashMap::new();
         for production in productions_array {
             if !production.is_object() {
                 return Err(LlkError::GrammarFromJsonFailed(
@@ -74,27 +84,25 @@ pub(super) fn parse_grammar_json(json_string: &str) -> Result<LlkGrammar, LlkErr
                 ));
             }
             if !production[PRODUCTION_NTERM_KEY].is_string() {
-                if !production.is_object() {
-                    return Err(LlkError::GrammarFromJsonFailed(
-                        "invalid production non-treminal definition format".to_string(),
-                    ));
-                }
+                return Err(LlkError::GrammarFromJsonFailed(
+                    "invalid production non-treminal definition format".to_string(),
+                ));
             }
             let production_nterm = production[PRODUCTION_NTERM_KEY].as_str().unwrap();
-            if production_nterm.len() != 1 {
+            if production_nterm.chars().count() != 1 {
                 return Err(LlkError::GrammarFromJsonFailed(
                     "invalid production non-terminal definition".to_string(),
                 ));
             }
-            let production_nterm = production_nterm.chars().nth(0).unwrap();
+            let production_nterm = production_nterm.chars().next().unwrap();
             let production_derivative = production.get(PRODUCTION_DERIVATIVE_KEY);
-            if let None = production_derivative {
+            if production_derivative.is_none() {
                 return Err(LlkError::GrammarFromJsonFailed(
                     "invalid production derivative definition".to_string(),
                 ));
             }
             let production_derivative = production_derivative.unwrap();
-            if !production_derivative.is_null() || !production_derivative.is_string() {
+            if !production_derivative.is_null() && !production_derivative.is_string() {
                 return Err(LlkError::GrammarFromJsonFailed(
                     "invalid production derivative definition".to_string(),
                 ));
@@ -107,18 +115,93 @@ pub(super) fn parse_grammar_json(json_string: &str) -> Result<LlkGrammar, LlkErr
                 Some(production_derivative.as_str().unwrap().to_owned())
             };
 
+            /* Production label (optional) */
+            let production_label = &production[PRODUCTION_LABEL_KEY];
+            if !production_label.is_null() {
+                if !production_label.is_string() {
+                    return Err(LlkError::GrammarFromJsonFailed(
+                        "invalid production label definition format".to_string(),
+                    ));
+                }
+                labels.insert(
+                    productions.len(),
+                    production_label.as_str().unwrap().to_owned(),
+                );
+            }
+
             productions.push((production_nterm, production_derivative))
         }
 
-        let productions = LlkGrammar::normalize_productions(productions, start_symbol);
-
-        let grammar = LlkGrammar::new(
+        let mut grammar = LlkGrammar::new(
             term_symbols,
             nterm_symbols,
             start_symbol,
             lookahead,
             productions,
-        )?;
+        )?
+        .with_labels(labels)?;
+
+        /* Case insensitivity (optional) */
+        let case_insensitive = &json_values[CASE_INSENSITIVE_KEY];
+        if !case_insensitive.is_null() && !case_insensitive.is_boolean() {
+            return Err(LlkError::GrammarFromJsonFailed(
+                "invalid case insensitivity definition format".to_string(),
+            ));
+        }
+        if case_insensitive.as_bool() == Some(true) {
+            grammar = grammar.with_case_insensitive()?;
+        }
+
+        /* Character classes (optional) */
+        let char_classes = &json_values[CHAR_CLASSES_KEY];
+        if !char_classes.is_null() {
+            if !char_classes.is_object() {
+                return Err(LlkError::GrammarFromJsonFailed(
+                    "invalid character classes definition format".to_string(),
+                ));
+            }
+            let mut char_classes_map = HashMap::new();
+            for (term, pattern) in char_classes.as_object().unwrap() {
+                if term.chars().count() != 1 || !pattern.is_string() {
+                    return Err(LlkError::GrammarFromJsonFailed(
+                        "invalid character class definition".to_string(),
+                    ));
+                }
+                let term = term.chars().next().unwrap();
+                let char_class = LlkCharClass::from_pattern(pattern.as_str().unwrap())?;
+
+                char_classes_map.insert(term, char_class);
+            }
+
+            grammar = grammar.with_char_classes(char_classes_map)?;
+        }
+
+        /* Skip rules (optional) */
+        let skip = &json_values[SKIP_KEY];
+        if !skip.is_null() {
+            if !skip.is_array() {
+                return Err(LlkError::GrammarFromJsonFailed(
+                    "invalid skip rules definition format".to_string(),
+                ));
+            }
+            let mut skip_rules = Vec::new();
+            for skip_rule in skip.as_array().unwrap() {
+                if let Some(pattern) = skip_rule.as_str() {
+                    skip_rules.push(LlkSkipRule::Class(LlkCharClass::from_pattern(pattern)?));
+                } else if let (Some(open), Some(close)) = (
+                    skip_rule[SKIP_OPEN_KEY].as_str(),
+                    skip_rule[SKIP_CLOSE_KEY].as_str(),
+                ) {
+                    skip_rules.push(LlkSkipRule::Delimited(open.to_owned(), close.to_owned()));
+                } else {
+                    return Err(LlkError::GrammarFromJsonFailed(
+                        "invalid skip rule definition".to_string(),
+                    ));
+                }
+            }
+
+            grammar = grammar.with_skip_rules(skip_rules)?;
+        }
 
         Ok(grammar)
     } else {
@@ -127,3 +210,96 @@ pub(super) fn parse_grammar_json(json_string: &str) -> Result<LlkGrammar, LlkErr
         ))
     }
 }
+
+#[test]
+fn grammar_json_test() {
+    let json_string = r##"{
+        "term_symbols": "ad",
+        "nterm_symbols": "SJ",
+        "start_symbol": "S",
+        "lookahead": 1,
+        "productions": [
+            { "nterm": "S", "derivative": "aJ" },
+            { "nterm": "J", "derivative": "aJ", "label": "letter" },
+            { "nterm": "J", "derivative": "dJ" },
+            { "nterm": "J", "derivative": null }
+        ],
+        "char_classes": { "a": "[a-z]", "d": "[0-9]" },
+        "skip": ["[ \t]", { "open": "#", "close": "\n" }],
+        "case_insensitive": true
+    }"##;
+
+    let grammar = LlkGrammar::from_json(json_string).unwrap();
+    assert_eq!(grammar.productions[0], ('S', Some("aJ".to_string())));
+    assert!(grammar.case_insensitive);
+    assert_eq!(grammar.production_label(1), Some("letter"));
+    assert_eq!(grammar.labeled_production("letter"), Some(1));
+    assert!(grammar.char_classes[&'a'].contains('Q'));
+    assert!(grammar.char_classes[&'d'].contains('7'));
+    assert_eq!(
+        grammar.skip_rules[1],
+        LlkSkipRule::Delimited("#".to_string(), "\n".to_string())
+    );
+
+    let json_string = json_string.replace("[0-9]", "[a-z]");
+    assert!(matches!(
+        LlkGrammar::from_json(&json_string),
+        Err(LlkError::InvalidGrammar(_))
+    ));
+
+    /* Production non-terminal symbol must be a string */
+    for nterm in ["1", "null", "[\"S\"]", "{ \"S\": \"S\" }"] {
+        let json_string =
+            json_string.replacen("\"nterm\": \"S\"", &format!("\"nterm\": {nterm}"), 1);
+        assert!(matches!(
+            LlkGrammar::from_json(&json_string),
+            Err(LlkError::GrammarFromJsonFailed(_))
+        ));
+    }
+    let json_string = json_string.replacen("\"nterm\": \"S\", ", "", 1);
+    assert!(matches!(
+        LlkGrammar::from_json(&json_string),
+        Err(LlkError::GrammarFromJsonFailed(_))
+    ));
+}
+
+#[test]
+fn grammar_json_productions_test() {
+    let grammar_json = |derivative: &str| {
+        format!(
+            r#"{{
+                "term_symbols": "a",
+                "nterm_symbols": "SA",
+                "start_symbol": "S",
+                "lookahead": 1,
+                "productions": [
+                    {{ "nterm": "S", "derivative": "aA" }},
+                    {{ "nterm": "A", "derivative": "a" }},
+                    {{ "nterm": "A"{} }}
+                ]
+            }}"#,
+            derivative
+        )
+    };
+
+    /* ε-production is given either by null or by the empty string, productions are normalized once */
+    for derivative in [r#", "derivative": null"#, r#", "derivative": """#] {
+        let grammar = LlkGrammar::from_json(&grammar_json(derivative)).unwrap();
+        assert_eq!(
+            grammar.productions,
+            vec![
+                ('S', Some("aA".to_string())),
+                ('A', Some("a".to_string())),
+                ('A', None)
+            ]
+        );
+    }
+
+    /* Derivative is mandatory and it is neither a number nor a list */
+    for derivative in ["", r#", "derivative": 1"#, r#", "derivative": ["a"]"#] {
+        assert!(matches!(
+            LlkGrammar::from_json(&grammar_json(derivative)),
+            Err(LlkError::GrammarFromJsonFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,25 @@
+use super::span::{LlkLocation, LlkSpan};
+use crate::error::LlkError;
+
+/*
+ * Receiver of parsing events in the order of the leftmost derivation.
+ * Every entered non-terminal symbol is exited after its whole subtree.
+ * Trivia is passed only when the parser keeps it, otherwise it is empty
+ */
+pub trait LlkParseListener {
+    /* Production is the index of the expanded one, none for a node made up by error recovery */
+    fn enter_nonterminal(&mut self, _nterm: char, _production: Option<usize>) {}
+
+    fn exit_nonterminal(&mut self, _nterm: char) {}
+
+    /* Span covers the symbol only, without its trivia */
+    fn terminal(&mut self, _symbol: char, _span: LlkSpan, _leading_trivia: &str) {}
+
+    /* Skipped input is empty when the error was recovered from by assuming a missing symbol */
+    fn error(&mut self, _error: &LlkError, _skipped: &str, _span: LlkSpan) {}
+
+    fn end_of_input(&mut self, _location: LlkLocation, _trailing_trivia: &str) {}
+}
+
+/* Listener ignoring all the events, parsing with it only recognizes the input */
+impl LlkParseListener for () {}
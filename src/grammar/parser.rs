@@ -1,111 +1,1009 @@
+mod scanner;
+mod stream;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io::Read;
+use std::ops::Range;
+use std::sync::OnceLock;
+
 use crate::error::LlkError;
-use crate::grammar::tree::LlkTree;
+use crate::grammar::actions::{LlkActionEvaluator, LlkActions};
+use crate::grammar::completion::LlkCompletion;
+use crate::grammar::listener::LlkParseListener;
+use crate::grammar::span::{LlkLocation, LlkSpan};
+use crate::grammar::tree::{LlkTree, LlkTreeBuilder};
 use crate::grammar::{LlkGrammar, LlkLut};
+use scanner::LlkInput;
+pub use stream::LlkParseSession;
+
+/* Parser stack item, exit item closes the subtree of an expanded non-terminal symbol */
+#[derive(Clone, Copy)]
+enum LlkStackItem {
+    Symbol(char),
+    Exit(char),
+}
+
+/*
+ * Parser stack counting the symbols which can not derive ε, so that
+ * accepting positions are found in constant time. Changes made since
+ * the last accepting position are journaled to be undone
+ */
+struct LlkStack<'a> {
+    items: Vec<LlkStackItem>,
+    nullable_nterms: Option<&'a HashSet<char>>,
+    non_nullable_count: usize,
+    /* Popped items are kept to be pushed back, pushes are marked with none */
+    journal: Vec<Option<LlkStackItem>>,
+}
+
+impl<'a> LlkStack<'a> {
+    fn new(items: Vec<LlkStackItem>, nullable_nterms: Option<&'a HashSet<char>>) -> LlkStack<'a> {
+        let mut stack = LlkStack {
+            items: Vec::new(),
+            nullable_nterms,
+            non_nullable_count: 0,
+            journal: Vec::new(),
+        };
+        stack.extend(items);
+        stack.journal.clear();
+
+        stack
+    }
+
+    fn last(&self) -> Option<&LlkStackItem> {
+        self.items.last()
+    }
+
+    fn push(&mut self, item: LlkStackItem) {
+        if let Some(nullable_nterms) = self.nullable_nterms {
+            self.non_nullable_count += LlkStack::is_non_nullable(nullable_nterms, item) as usize;
+            self.journal.push(None);
+        }
+        self.items.push(item);
+    }
+
+    fn extend<I: IntoIterator<Item = LlkStackItem>>(&mut self, items: I) {
+        for item in items {
+            self.push(item);
+        }
+    }
+
+    fn pop(&mut self) -> Option<LlkStackItem> {
+        let item = self.items.pop()?;
+        if let Some(nullable_nterms) = self.nullable_nterms {
+            self.non_nullable_count -= LlkStack::is_non_nullable(nullable_nterms, item) as usize;
+            self.journal.push(Some(item));
+        }
+        Some(item)
+    }
+
+    /* Input could end here, every symbol left derives ε. Journal starts anew */
+    fn accept(&mut self) -> bool {
+        if self.nullable_nterms.is_none() || self.non_nullable_count > 0 {
+            return false;
+        }
+
+        self.journal.clear();
+        true
+    }
+
+    /* Return to the last accepting position */
+    fn rollback(&mut self) {
+        for change in std::mem::take(&mut self.journal).into_iter().rev() {
+            match change {
+                Some(item) => self.push(item),
+                None => {
+                    self.pop();
+                }
+            }
+        }
+        self.journal.clear();
+    }
+
+    fn is_non_nullable(nullable_nterms: &HashSet<char>, item: LlkStackItem) -> bool {
+        match item {
+            LlkStackItem::Symbol(symbol) => {
+                symbol != LlkGrammar::EOF && !nullable_nterms.contains(&symbol)
+            }
+            LlkStackItem::Exit(_) => false,
+        }
+    }
+}
+
+/*
+ * Longest prefix parsing, see LlkParser::parse_prefix. Listener events are
+ * committed at every accepting position and discarded past the last one
+ */
+struct LlkPrefix<L> {
+    commit: fn(&mut L, bool),
+    accept: Option<usize>,
+    truncated: bool,
+}
+
+/* Parsing event held back until its accepting position is reached */
+enum LlkPendingEvent {
+    Enter(char, Option<usize>),
+    Exit(char),
+    Terminal(char, LlkSpan, String),
+    EndOfInput(LlkLocation, String),
+}
+
+/*
+ * Tree builder of the longest prefix. Errors are never committed,
+ * parsing either returns to the last accepting position or fails
+ */
+struct LlkPrefixTreeBuilder {
+    tree_builder: LlkTreeBuilder,
+    pending: Vec<LlkPendingEvent>,
+}
+
+impl LlkPrefixTreeBuilder {
+    fn commit(&mut self, accept: bool) {
+        if !accept {
+            self.pending.clear();
+            return;
+        }
+
+        for event in self.pending.drain(..) {
+            match event {
+                LlkPendingEvent::Enter(nterm, production) => {
+                    self.tree_builder.enter_nonterminal(nterm, production)
+                }
+                LlkPendingEvent::Exit(nterm) => self.tree_builder.exit_nonterminal(nterm),
+                LlkPendingEvent::Terminal(symbol, span, trivia) => {
+                    self.tree_builder.terminal(symbol, span, &trivia)
+                }
+                LlkPendingEvent::EndOfInput(location, trivia) => {
+                    self.tree_builder.end_of_input(location, &trivia)
+                }
+            }
+        }
+    }
+}
+
+impl LlkParseListener for LlkPrefixTreeBuilder {
+    fn enter_nonterminal(&mut self, nterm: char, production: Option<usize>) {
+        self.pending.push(LlkPendingEvent::Enter(nterm, production));
+    }
+
+    fn exit_nonterminal(&mut self, nterm: char) {
+        self.pending.push(LlkPendingEvent::Exit(nterm));
+    }
+
+    fn terminal(&mut self, symbol: char, span: LlkSpan, leading_trivia: &str) {
+        self.pending.push(LlkPendingEvent::Terminal(
+            symbol,
+            span,
+            leading_trivia.to_owned(),
+        ));
+    }
+
+    fn end_of_input(&mut self, location: LlkLocation, trailing_trivia: &str) {
+        self.pending.push(LlkPendingEvent::EndOfInput(
+            location,
+            trailing_trivia.to_owned(),
+        ));
+    }
+}
 
 pub struct LlkParser {
     grammar: LlkGrammar,
     lut: LlkLut,
+    entry_luts: HashMap<char, OnceLock<Result<LlkLut, String>>>,
+    nullable_nterms: HashSet<char>,
+    keep_trivia: bool,
+    explicit_nodes: bool,
 }
 
 impl LlkParser {
     pub fn new(grammar: LlkGrammar) -> LlkParser {
+        /* Lookup tables of other entry symbols are built on first use */
+        let entry_luts = grammar
+            .nterm_symbols
+            .iter()
+            .filter(|nterm| **nterm != grammar.start_symbol)
+            .map(|nterm| (*nterm, OnceLock::new()))
+            .collect();
+
+        let nullable_nterms = grammar
+            .nterm_symbols
+            .iter()
+            .copied()
+            .filter(|nterm| grammar.is_nullable(*nterm))
+            .collect();
+
         LlkParser {
             lut: LlkParser::create_lut(&grammar),
             grammar,
+            entry_luts,
+            nullable_nterms,
+            keep_trivia: false,
+            explicit_nodes: false,
         }
     }
 
+    pub fn with_trivia(mut self) -> LlkParser {
+        self.keep_trivia = true;
+        self
+    }
+
+    /* Trees get explicit ε nodes and the end marker leaf, see LlkTreeBuilder::with_explicit_nodes */
+    pub fn with_explicit_nodes(mut self) -> LlkParser {
+        self.explicit_nodes = true;
+        self
+    }
+
     pub fn parse(&self, string: &str) -> Result<LlkTree, LlkError> {
-        super::grammar_assert::assert_term_string(&self.grammar, string)?;
+        let input = LlkInput::scan(&self.grammar, string, self.keep_trivia);
+
+        super::grammar_assert::assert_term_string(&self.grammar, &input.get_term_string())?;
+
+        let (tree_root, mut errors) = self.derive_tree(self.grammar.start_symbol, &input, false);
+
+        if let Some(error) = errors.pop() {
+            Err(error)
+        } else {
+            Ok(tree_root)
+        }
+    }
 
-        let target_string: String = format!("{}{}", string, LlkGrammar::EOF);
-        let lookahead_len = self.grammar.lookahead;
-        let mut stack = Vec::new();
-        let mut tree_root = LlkTree::new(self.grammar.start_symbol);
-        let mut tree_node_stack = Vec::new();
+    /* Check whether the input belongs to the language, no tree is built */
+    pub fn recognize(&self, string: &str) -> bool {
+        self.recognize_with_error(string).is_ok()
+    }
+
+    /* Recognize the input reporting the first error along with its position */
+    pub fn recognize_with_error(&self, string: &str) -> Result<(), LlkError> {
+        self.derive_input(&LlkInput::scan(&self.grammar, string, false), &mut ())
+    }
+
+    /* Parse the input evaluating semantic actions bottom-up instead of building the tree */
+    pub fn parse_with_actions<T>(
+        &self,
+        string: &str,
+        actions: &LlkActions<T>,
+    ) -> Result<T, LlkError> {
+        let mut evaluator = LlkActionEvaluator::new(&self.grammar, actions)?;
+        self.parse_with_listener(string, &mut evaluator)?;
+        evaluator.into_value()
+    }
 
-        /* Init stack with the start symbol */
-        stack.push(self.grammar.start_symbol);
+    /* Parse the input notifying the listener instead of building the tree */
+    pub fn parse_with_listener<L: LlkParseListener>(
+        &self,
+        string: &str,
+        listener: &mut L,
+    ) -> Result<(), LlkError> {
+        let input = LlkInput::scan(&self.grammar, string, self.keep_trivia);
+        self.derive_input(&input, listener)
+    }
 
-        /* Init tree node stack with root tree */
-        tree_node_stack.push(&mut tree_root as *mut LlkTree);
+    /*
+     * Parse input pulled lazily from a character iterator. Unknown symbols
+     * are reported as they are met rather than before parsing starts
+     */
+    pub fn parse_chars<I>(&self, chars: I) -> Result<LlkTree, LlkError>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut session = self.session();
 
+        for symbol in chars {
+            session.push_char(symbol);
+            if session.has_failed() {
+                break;
+            }
+        }
+
+        session.finish()
+    }
+
+    pub fn parse_reader<R: Read>(&self, reader: R) -> Result<LlkTree, LlkError> {
+        let mut session = self.session();
+        stream::read_utf8(reader, &mut session)?;
+        session.finish()
+    }
+
+    /* Start parsing input fed in chunks */
+    pub fn session(&self) -> LlkParseSession<'_> {
+        LlkParseSession::new(self)
+    }
+
+    pub fn parse_with_recovery(&self, string: &str) -> (LlkTree, Vec<LlkError>) {
+        self.derive_tree(
+            self.grammar.start_symbol,
+            &LlkInput::scan(&self.grammar, string, self.keep_trivia),
+            true,
+        )
+    }
+
+    /*
+     * Parse the longest prefix of the input forming a valid sentence.
+     * Returns the tree along with the number of consumed characters,
+     * skipped fragments preceding the unconsumed rest included
+     */
+    pub fn parse_prefix(&self, string: &str) -> Result<(LlkTree, usize), LlkError> {
+        let input = LlkInput::scan(&self.grammar, string, self.keep_trivia);
+        let mut prefix = LlkPrefix {
+            commit: LlkPrefixTreeBuilder::commit,
+            accept: None,
+            truncated: false,
+        };
+        let mut prefix_builder = LlkPrefixTreeBuilder {
+            tree_builder: self.create_tree_builder(self.grammar.start_symbol),
+            pending: Vec::new(),
+        };
+
+        let mut errors = self.derive(
+            self.grammar.start_symbol,
+            &input,
+            false,
+            Some(&mut prefix),
+            &mut prefix_builder,
+        );
+
+        if let Some(error) = errors.pop() {
+            return Err(error);
+        }
+
+        let tree_root = prefix_builder.tree_builder.into_tree().unwrap();
+        match prefix.accept {
+            Some(accept) if prefix.truncated => Ok((tree_root, input.locations[accept].position)),
+            _ => Ok((tree_root, string.chars().count())),
+        }
+    }
+
+    /*
+     * Suggest what may follow an incomplete input. Parser runs through the
+     * input until the next decision needs lookahead beyond its end
+     */
+    pub fn complete(&self, prefix: &str) -> LlkCompletion {
+        let input = LlkInput::scan(&self.grammar, prefix, false);
+        let target_string = &input.symbols[..input.symbols.len() - 1];
+
+        /*
+         * Each stack item holds a grammar symbol and the number of its
+         * ancestors, so that the path of open non-terminals can be restored
+         */
+        let mut stack = vec![(LlkGrammar::EOF, 0), (self.grammar.start_symbol, 0)];
+        let mut open_nterms = Vec::new();
         let mut lookahead_start = 0;
 
-        while lookahead_start < string.len() {
-            let string_rest = &target_string[lookahead_start..];
+        while let Some(&(top, depth)) = stack.last() {
+            open_nterms.truncate(depth);
 
-            /* Get the top of the stack */
-            let top = *stack.last().unwrap();
+            if self.grammar.is_term(top) {
+                if lookahead_start == target_string.len() {
+                    /* Lookahead strings derived from the rest of the stack start with the top */
+                    let symbols: String =
+                        stack.iter().rev().map(|(symbol, _depth)| symbol).collect();
+                    let lookaheads = self.grammar.get_term_prefixes(&symbols);
+
+                    return LlkCompletion::new(lookaheads.into_iter().collect(), open_nterms, true);
+                }
+
+                if !self
+                    .grammar
+                    .term_matches(top, target_string[lookahead_start])
+                {
+                    break;
+                }
 
-            /*
-             * If stack top matches first symbol in the rest of the string
-             * pop stack top symbol and continue from next input string symbol
-             */
-            if self.grammar.is_term(top) && top == string_rest.chars().nth(0).unwrap() {
                 stack.pop();
                 lookahead_start += 1;
+            } else if lookahead_start + self.grammar.lookahead > target_string.len() {
+                /*
+                 * Typed symbols have to match the beginning of the predicting lookahead.
+                 * LUT keys merge FOLLOW sets of all the occurrences of the stack top,
+                 * so each of them is checked against the rest of the stack as well
+                 */
+                let typed = &target_string[lookahead_start..];
+                let symbols: Vec<char> = stack.iter().map(|(symbol, _depth)| *symbol).collect();
+                let lookaheads: Vec<String> = self
+                    .lut
+                    .get(&top)
+                    .into_iter()
+                    .flat_map(|row| row.keys())
+                    .filter_map(|lookahead| {
+                        let lookahead: Vec<char> = lookahead.chars().collect();
+                        if lookahead.len() > typed.len()
+                            && lookahead
+                                .iter()
+                                .zip(typed)
+                                .all(|(term, symbol)| self.grammar.term_matches(*term, *symbol))
+                            && self.is_continuation(symbols.clone(), &lookahead)
+                        {
+                            Some(lookahead[typed.len()..].iter().collect())
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+
+                /* Stack top is predicted, yet it is open as well */
+                open_nterms.push(top);
+
+                let viable = !lookaheads.is_empty();
+                return LlkCompletion::new(lookaheads, open_nterms, viable);
+            } else if let Some(production) = self.find_production(
+                &self.lut,
+                top,
+                &self.get_lookahead(&input.symbols, lookahead_start),
+            ) {
+                stack.pop();
+                open_nterms.push(top);
+
+                stack.extend(
+                    self.get_production_rhs(production)
+                        .chars()
+                        .rev()
+                        .map(|symbol| (symbol, depth + 1)),
+                );
             } else {
-                let lookahead = if string_rest.len() >= lookahead_len {
-                    string_rest[..lookahead_len].to_owned()
-                } else {
-                    string_rest[..].to_owned()
-                };
+                break;
+            }
+        }
+
+        LlkCompletion::new(Vec::new(), open_nterms, false)
+    }
+
+    pub fn is_viable_prefix(&self, prefix: &str) -> bool {
+        self.complete(prefix).is_viable_prefix()
+    }
+
+    /*
+     * Parse the input as a fragment derived from the given non-terminal symbol.
+     * Lookup table for the non-terminal is built on first use, since the
+     * lookahead following the fragment differs from the one of a whole sentence
+     */
+    pub fn parse_as(&self, nterm: char, string: &str) -> Result<LlkTree, LlkError> {
+        self.get_lut(nterm)?;
+
+        if nterm == self.grammar.start_symbol {
+            return self.parse(string);
+        }
+
+        let input = LlkInput::scan(&self.grammar, string, self.keep_trivia);
+
+        super::grammar_assert::assert_term_string(&self.grammar, &input.get_term_string())?;
+
+        let (tree_root, mut errors) = self.derive_tree(nterm, &input, false);
+
+        if let Some(error) = errors.pop() {
+            Err(error)
+        } else {
+            Ok(tree_root)
+        }
+    }
+
+    /* Parse a fragment derived from the given non-terminal symbol, see parse_with_recovery */
+    pub fn parse_as_with_recovery(
+        &self,
+        nterm: char,
+        string: &str,
+    ) -> Result<(LlkTree, Vec<LlkError>), LlkError> {
+        self.get_lut(nterm)?;
 
-                if let Some(production_rhs) = self.lut.get(&(top, lookahead)) {
+        Ok(self.derive_tree(
+            nterm,
+            &LlkInput::scan(&self.grammar, string, self.keep_trivia),
+            true,
+        ))
+    }
+}
+
+impl LlkParser {
+    /*
+     * Drive the parser through the whole input including the end marker.
+     * Parsing succeeds only when the symbol stack is exhausted, that is
+     * when the end marker at the bottom of the stack has been matched.
+     * Without recovery parsing stops at the first error.
+     * Parsing a prefix returns from an error to the last accepting position,
+     * where every symbol left on the stack derives ε, and goes on with the
+     * input cut there. LL(k) grammar being unambiguous, the derivation so far
+     * is the one of the cut input
+     */
+    fn derive<L: LlkParseListener>(
+        &self,
+        entry: char,
+        input: &LlkInput,
+        recover: bool,
+        mut prefix: Option<&mut LlkPrefix<L>>,
+        listener: &mut L,
+    ) -> Vec<LlkError> {
+        /*
+         * Expanded non-terminal symbol leaves an exit item below its production
+         * RHS, so that the listener is notified once its subtree is derived.
+         * The end marker lies below the entry symbol, a sentence ends once
+         */
+        let mut stack = LlkStack::new(
+            vec![
+                LlkStackItem::Symbol(LlkGrammar::EOF),
+                LlkStackItem::Symbol(entry),
+            ],
+            prefix.as_ref().map(|_| &self.nullable_nterms),
+        );
+
+        let errors = self.derive_stack(
+            entry,
+            input,
+            recover,
+            &mut stack,
+            prefix.as_deref_mut(),
+            listener,
+        );
+
+        if let Some(prefix) = prefix {
+            if let (Some(accept), false) = (prefix.accept, errors.is_empty()) {
+                (prefix.commit)(listener, false);
+                stack.rollback();
+                prefix.truncated = true;
+
+                return self.derive_stack(
+                    entry,
+                    &input.truncate(accept),
+                    recover,
+                    &mut stack,
+                    Some(prefix),
+                    listener,
+                );
+            }
+        }
+
+        errors
+    }
+
+    /* Drive the parser from the given stack, input cut for a prefix is derived from the cut on */
+    fn derive_stack<L: LlkParseListener>(
+        &self,
+        entry: char,
+        input: &LlkInput,
+        recover: bool,
+        stack: &mut LlkStack,
+        mut prefix: Option<&mut LlkPrefix<L>>,
+        listener: &mut L,
+    ) -> Vec<LlkError> {
+        let lut = match self.get_lut(entry) {
+            Ok(lut) => lut,
+            Err(error) => return vec![error],
+        };
+        let target_string = &input.symbols;
+        let mut errors = Vec::new();
+        let mut lookahead_start = match prefix.as_ref() {
+            Some(prefix) if prefix.truncated => prefix.accept.unwrap(),
+            _ => 0,
+        };
+
+        while let Some(&item) = stack.last() {
+            if let Some(prefix) = prefix.as_mut() {
+                if stack.accept() && !prefix.truncated {
+                    (prefix.commit)(listener, true);
+                    prefix.accept = Some(lookahead_start);
+                }
+            }
+
+            let top = match item {
+                LlkStackItem::Symbol(symbol) => symbol,
+                LlkStackItem::Exit(nterm) => {
                     stack.pop();
+                    listener.exit_nonterminal(nterm);
+                    continue;
+                }
+            };
+            let current = target_string[lookahead_start];
+
+            if self.grammar.is_term(top) {
+                if self.grammar.term_matches(top, current) {
+                    /* Stack top matches current input symbol */
+                    stack.pop();
+
+                    let trivia = &input.trivia[lookahead_start];
+                    if top != LlkGrammar::EOF {
+                        let span = input.get_span(lookahead_start, lookahead_start + 1);
+                        listener.terminal(current, span, trivia);
+                    } else {
+                        listener.end_of_input(input.locations[lookahead_start], trivia);
+                    }
+
+                    lookahead_start += 1;
+                } else if !recover {
+                    let error = LlkParser::unexpected_symbol_error(
+                        current,
+                        input.locations[lookahead_start].position,
+                        vec![top.to_string()],
+                    );
+                    self.report_error(
+                        listener,
+                        &mut errors,
+                        error,
+                        input,
+                        lookahead_start..lookahead_start,
+                    );
+                    break;
+                } else if top == LlkGrammar::EOF {
+                    /* Input continues after a complete sentence, skip the rest of it */
+                    let error = LlkError::UnexpectedSymbol {
+                        symbol: current,
+                        position: input.locations[lookahead_start].position,
+                        expected: vec![LlkGrammar::EOF.to_string()],
+                    };
+
+                    let skipped_end = target_string.len() - 1;
+                    let skipped = lookahead_start..skipped_end;
+                    lookahead_start = skipped_end;
+
+                    self.report_error(listener, &mut errors, error, input, skipped);
+                } else if current != LlkGrammar::EOF
+                    && target_string
+                        .get(lookahead_start + 1)
+                        .is_some_and(|next| self.grammar.term_matches(top, *next))
+                {
+                    /* Single symbol deletion: next input symbol matches the stack top */
+                    let error = LlkError::UnexpectedSymbol {
+                        symbol: current,
+                        position: input.locations[lookahead_start].position,
+                        expected: vec![top.to_string()],
+                    };
+
+                    let skipped = lookahead_start..lookahead_start + 1;
+                    lookahead_start += 1;
+
+                    self.report_error(listener, &mut errors, error, input, skipped);
+                } else {
+                    /* Single symbol insertion: assume that missing terminal was there */
+                    let error = LlkError::MissingSymbol {
+                        symbol: top,
+                        position: input.locations[lookahead_start].position,
+                    };
+                    stack.pop();
+
+                    self.report_error(
+                        listener,
+                        &mut errors,
+                        error,
+                        input,
+                        lookahead_start..lookahead_start,
+                    );
+                }
+            } else if let Some(production) = self.find_production(
+                lut,
+                top,
+                &self.get_lookahead(target_string, lookahead_start),
+            ) {
+                stack.pop();
+                listener.enter_nonterminal(top, Some(production));
+
+                /* Push production RHS to the stack */
+                stack.push(LlkStackItem::Exit(top));
+                stack.extend(
+                    self.get_production_rhs(production)
+                        .chars()
+                        .rev()
+                        .map(LlkStackItem::Symbol),
+                );
+            } else {
+                let error = LlkParser::unexpected_symbol_error(
+                    current,
+                    input.locations[lookahead_start].position,
+                    self.get_expected(lut, top),
+                );
+
+                if !recover {
+                    self.report_error(
+                        listener,
+                        &mut errors,
+                        error,
+                        input,
+                        lookahead_start..lookahead_start,
+                    );
+                    break;
+                } else if current != LlkGrammar::EOF
+                    && self
+                        .find_production(
+                            lut,
+                            top,
+                            &self.get_lookahead(target_string, lookahead_start + 1),
+                        )
+                        .is_some()
+                {
+                    /* Single symbol deletion: production is predicted by the next input symbol */
+                    let skipped = lookahead_start..lookahead_start + 1;
+                    lookahead_start += 1;
+
+                    self.report_error(listener, &mut errors, error, input, skipped);
+                } else {
+                    /*
+                     * Panic mode: skip input symbols until either a production of
+                     * the stack top is predicted or a synchronization symbol
+                     * from the FOLLOW set of the stack top is found
+                     */
+                    let sync_symbols = self.get_sync_symbols(entry, top);
+                    let skipped_start = lookahead_start;
+
+                    loop {
+                        let current = target_string[lookahead_start];
+
+                        if self
+                            .find_production(
+                                lut,
+                                top,
+                                &self.get_lookahead(target_string, lookahead_start),
+                            )
+                            .is_some()
+                        {
+                            let skipped = skipped_start..lookahead_start;
+                            self.report_error(listener, &mut errors, error, input, skipped);
+                            break;
+                        }
+
+                        if sync_symbols
+                            .iter()
+                            .any(|symbol| self.grammar.term_matches(*symbol, current))
+                        {
+                            /* Give up on the stack top, leave a node with an error inside */
+                            stack.pop();
 
-                    /* Push production RHS to the stack */
-                    stack.extend(production_rhs.chars().rev());
-
-                    /* Update derivation tree */
-                    let tree_node_stack_top = tree_node_stack.pop().unwrap();
-
-                    for symbol in production_rhs.chars() {
-                        if self.grammar.is_nterm(symbol) {
-                            unsafe {
-                                let new_node = (*tree_node_stack_top).push_node(symbol);
-                                tree_node_stack.push(new_node);
-                            }
-                        } else if symbol != LlkGrammar::EOF {
-                            unsafe {
-                                (*tree_node_stack_top).push_leaf(symbol);
-                            }
+                            let skipped = skipped_start..lookahead_start;
+                            listener.enter_nonterminal(top, None);
+                            self.report_error(listener, &mut errors, error, input, skipped);
+                            listener.exit_nonterminal(top);
+                            break;
                         }
+
+                        lookahead_start += 1;
                     }
+                }
+            }
+        }
+
+        /* Events past the last accepting position make up the rest of the prefix */
+        if let Some(prefix) = prefix {
+            if errors.is_empty() {
+                (prefix.commit)(listener, true);
+            }
+        }
+
+        errors
+    }
+
+    /* Parse the whole input as a sentence notifying the listener */
+    fn derive_input<L: LlkParseListener>(
+        &self,
+        input: &LlkInput,
+        listener: &mut L,
+    ) -> Result<(), LlkError> {
+        super::grammar_assert::assert_term_string(&self.grammar, &input.get_term_string())?;
+
+        if let Some(error) = self
+            .derive(self.grammar.start_symbol, input, false, None, listener)
+            .pop()
+        {
+            Err(error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /* Parse the input building the tree, the entry symbol node is always there */
+    fn derive_tree(
+        &self,
+        entry: char,
+        input: &LlkInput,
+        recover: bool,
+    ) -> (LlkTree, Vec<LlkError>) {
+        let mut tree_builder = self.create_tree_builder(entry);
+        let errors = self.derive(entry, input, recover, None, &mut tree_builder);
+        let tree_root = tree_builder
+            .into_tree()
+            .unwrap_or_else(|| LlkTree::new(entry));
+
+        (tree_root, errors)
+    }
+
+    /* Fragments derived from other entry symbols are not sentences, they get no end marker leaf */
+    fn create_tree_builder(&self, entry: char) -> LlkTreeBuilder {
+        let tree_builder = LlkTreeBuilder::new().with_production_labels(&self.grammar);
+
+        if !self.explicit_nodes {
+            tree_builder
+        } else if entry == self.grammar.start_symbol {
+            tree_builder.with_explicit_nodes()
+        } else {
+            tree_builder.with_explicit_nodes().without_end_marker()
+        }
+    }
+
+    /* Input symbols from start to end are skipped by the error recovery */
+    fn report_error<L: LlkParseListener>(
+        &self,
+        listener: &mut L,
+        errors: &mut Vec<LlkError>,
+        error: LlkError,
+        input: &LlkInput,
+        skipped: Range<usize>,
+    ) {
+        let span = input.get_span(skipped.start, skipped.end);
+        let skipped = input.get_text(skipped.start, skipped.end);
+        listener.error(&error, &skipped, span);
+        errors.push(error);
+    }
+
+    /*
+     * Check that the stack could be derived further by the string of terminal
+     * symbols. Decisions requiring lookahead beyond the string are given the
+     * benefit of the doubt
+     */
+    fn is_continuation(&self, mut stack: Vec<char>, string: &[char]) -> bool {
+        let mut position = 0;
+
+        while let Some(&top) = stack.last() {
+            if position == string.len() {
+                break;
+            }
+
+            if self.grammar.is_term(top) {
+                if !self.grammar.terms_intersect(top, string[position]) {
+                    return false;
+                }
+
+                stack.pop();
+                position += 1;
+            } else {
+                let end = std::cmp::min(position + self.grammar.lookahead, string.len());
+                let lookahead: String = string[position..end].iter().collect();
+
+                if end - position < self.grammar.lookahead && string[end - 1] != LlkGrammar::EOF {
+                    break;
+                }
+
+                if let Some((_, production)) = self.lut.get(&top).and_then(|row| {
+                    row.iter().find(|(lut_lookahead, _)| {
+                        self.grammar
+                            .term_strings_intersect(lut_lookahead, &lookahead)
+                    })
+                }) {
+                    stack.pop();
+                    stack.extend(self.get_production_rhs(*production).chars().rev());
                 } else {
-                    return Err(LlkError::DerivationFailed(String::default()));
+                    return false;
                 }
             }
         }
 
-        Ok(tree_root)
+        true
+    }
+
+    fn unexpected_symbol_error(symbol: char, position: usize, expected: Vec<String>) -> LlkError {
+        if symbol == LlkGrammar::EOF {
+            LlkError::UnexpectedEndOfInput { position, expected }
+        } else {
+            LlkError::UnexpectedSymbol {
+                symbol,
+                position,
+                expected,
+            }
+        }
+    }
+
+    fn find_production(&self, lut: &LlkLut, nterm: char, lookahead: &str) -> Option<usize> {
+        let row = lut.get(&nterm)?;
+
+        if self.grammar.char_classes.is_empty() {
+            row.get(&self.grammar.fold_term_string(lookahead)).copied()
+        } else {
+            /* Lookahead symbols have to be matched against the classes in the row keys */
+            row.iter()
+                .find(|(lut_lookahead, _)| {
+                    self.grammar.term_string_matches(lut_lookahead, lookahead)
+                })
+                .map(|(_, production)| *production)
+        }
+    }
+
+    fn get_production_rhs(&self, production: usize) -> &str {
+        self.grammar.productions[production]
+            .1
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    fn get_lookahead(&self, target_string: &[char], start: usize) -> String {
+        let end = std::cmp::min(start + self.grammar.lookahead, target_string.len());
+        target_string[start..end].iter().collect()
+    }
+
+    fn get_expected(&self, lut: &LlkLut, nterm: char) -> Vec<String> {
+        let mut expected: Vec<String> = lut
+            .get(&nterm)
+            .into_iter()
+            .flat_map(|row| row.keys().cloned())
+            .collect();
+        expected.sort();
+        expected
+    }
+
+    fn get_sync_symbols(&self, entry: char, nterm: char) -> HashSet<char> {
+        let follow_set = self.grammar.follow_from(entry, nterm);
+
+        let mut sync_symbols: HashSet<char> =
+            follow_set.iter().filter_map(|s| s.chars().next()).collect();
+
+        /* End of input always synchronizes, so recovery is guaranteed to terminate */
+        sync_symbols.insert(LlkGrammar::EOF);
+        sync_symbols
     }
-}
 
-impl LlkParser {
     fn create_lut(grammar: &LlkGrammar) -> LlkLut {
         let mut lut = LlkLut::new();
 
-        for production in &grammar.productions {
-            let prod_nterm = production.0;
-            let prod_derivative = if let Some(derivative) = &production.1 {
-                derivative.clone()
-            } else {
-                String::default()
-            };
+        let choise_sets = grammar.choise_sets_from(grammar.start_symbol);
 
-            let choise_set = grammar.choise(production);
+        for (prod_index, production) in grammar.productions.iter().enumerate() {
+            let prod_nterm = production.0;
 
-            lut.extend(
-                std::iter::repeat(prod_nterm)
-                    .zip(choise_set)
-                    .zip(std::iter::repeat(prod_derivative)),
+            /* Case insensitive grammars are looked up by case folded lookahead */
+            lut.entry(prod_nterm).or_default().extend(
+                choise_sets[prod_index]
+                    .iter()
+                    .map(|choise| (grammar.fold_term_string(choise), prod_index)),
             );
         }
 
         lut
     }
+
+    fn get_lut(&self, entry: char) -> Result<&LlkLut, LlkError> {
+        if entry == self.grammar.start_symbol {
+            return Ok(&self.lut);
+        }
+
+        let entry_lut = self.entry_luts.get(&entry).ok_or_else(|| {
+            LlkError::IllegalOperation(format!(
+                "parsing as a terminal symbol \"{}\"",
+                entry.escape_default()
+            ))
+        })?;
+
+        entry_lut
+            .get_or_init(|| LlkParser::create_entry_lut(&self.grammar, entry))
+            .as_ref()
+            .map_err(|description| LlkError::InvalidGrammar(description.clone()))
+    }
+
+    fn create_entry_lut(grammar: &LlkGrammar, entry: char) -> Result<LlkLut, String> {
+        let mut lut = LlkLut::new();
+        let mut choise_sets: Vec<(char, HashSet<String>)> = Vec::new();
+
+        for (prod_index, choise_set) in grammar.choise_sets_from(entry).drain(..).enumerate() {
+            let production = &grammar.productions[prod_index];
+            let prod_nterm = production.0;
+
+            /*
+             * Grammar is LL(k) for its start symbol, yet a different end of
+             * sentence may bring productions of the same non-terminal into conflict
+             */
+            if choise_sets.iter().any(|(nterm, other_set)| {
+                *nterm == prod_nterm && grammar.choise_sets_intersect(&choise_set, other_set)
+            }) {
+                return Err(format!(
+                    "grammar is not LL({}) for entry symbol \"{}\" at production {}",
+                    grammar.lookahead,
+                    entry.escape_default(),
+                    LlkGrammar::format_production(production)
+                ));
+            }
+
+            lut.entry(prod_nterm).or_default().extend(
+                choise_set
+                    .iter()
+                    .map(|choise| (grammar.fold_term_string(choise), prod_index)),
+            );
+            choise_sets.push((prod_nterm, choise_set));
+        }
+
+        Ok(lut)
+    }
 }
 
 #[test]
@@ -120,6 +1018,10 @@ fn create_lut_test() {
             ('A', Some("aA".to_string())),
             ('A', Some("a".to_string())),
         ],
+        char_classes: std::collections::HashMap::new(),
+        skip_rules: Vec::new(),
+        case_insensitive: false,
+        labels: std::collections::HashMap::new(),
     };
 
     println!("Here comes the LUT: {:?}", LlkParser::create_lut(&grammar));
@@ -150,8 +1052,801 @@ fn parsing_test() {
     println!("LUT: {:?}", parser.lut);
 
     let tree = parser.parse("aaab").unwrap();
-    for symbol in &tree {
-        print!("{}", symbol);
+    for node in &tree {
+        if let Some(symbol) = node.symbol() {
+            print!("{}", symbol);
+        }
     }
     println!();
 }
+
+#[test]
+fn parsing_with_recovery_test() {
+    use crate::grammar::expression_grammar;
+
+    let grammar = expression_grammar();
+
+    let parser = LlkParser::new(grammar);
+    let terms = |tree: &LlkTree| -> String {
+        tree.iter()
+            .filter_map(|node| node.symbol())
+            .filter(|c| parser.grammar.is_term(*c))
+            .collect()
+    };
+
+    /* Valid input produces no errors */
+    let (tree, errors) = parser.parse_with_recovery("(a+a)+a");
+    assert!(errors.is_empty());
+    assert!(!tree.has_errors());
+    assert_eq!(terms(&tree), "(a+a)+a");
+
+    /* Unexpected symbol is deleted */
+    let (tree, errors) = parser.parse_with_recovery("a++a");
+    assert!(matches!(
+        errors.as_slice(),
+        [LlkError::UnexpectedSymbol {
+            symbol: '+',
+            position: 2,
+            ..
+        }]
+    ));
+    assert!(tree.has_errors());
+    assert_eq!(terms(&tree), "a+a");
+
+    /* Missing symbol is inserted */
+    let (tree, errors) = parser.parse_with_recovery("(a+a");
+    assert!(matches!(
+        errors.as_slice(),
+        [LlkError::MissingSymbol {
+            symbol: ')',
+            position: 4
+        }]
+    ));
+    assert!(tree.has_errors());
+    assert_eq!(terms(&tree), "(a+a");
+
+    /* Incomplete input is synchronized on the end of input */
+    let (tree, errors) = parser.parse_with_recovery("a+");
+    assert!(matches!(
+        errors.as_slice(),
+        [LlkError::UnexpectedEndOfInput { position: 2, .. }]
+    ));
+    assert!(tree.has_errors());
+    assert_eq!(terms(&tree), "a+");
+
+    /* Every error is recorded and trailing input is skipped */
+    let (tree, errors) = parser.parse_with_recovery("(a+)+a)a");
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        errors[0],
+        LlkError::UnexpectedSymbol {
+            symbol: ')',
+            position: 3,
+            ..
+        }
+    ));
+    assert!(matches!(
+        errors[1],
+        LlkError::UnexpectedSymbol {
+            symbol: ')',
+            position: 6,
+            ..
+        }
+    ));
+    assert!(tree.has_errors());
+    assert_eq!(terms(&tree), "(a+)+a");
+
+    /* Symbol skipped before the root is entered is kept in the tree */
+    let (tree, errors) = parser.parse_with_recovery("+a");
+    assert!(matches!(
+        errors.as_slice(),
+        [LlkError::UnexpectedSymbol {
+            symbol: '+',
+            position: 0,
+            ..
+        }]
+    ));
+    assert!(tree.has_errors());
+    assert_eq!(tree.source_text(), "+a");
+    assert_eq!(tree.span(tree.root()).start.position, 0);
+}
+
+#[test]
+fn strict_acceptance_test() {
+    let term_symbols = vec!['a', 'b', 'c'].drain(..).collect();
+    let nterm_symbols = vec!['S', 'A', 'B'].drain(..).collect();
+    let start_symbol = 'S';
+    let lookahead = 1;
+    let productions = vec![
+        ('S', Some("aAc".to_string())),
+        ('S', Some("bB".to_string())),
+        ('A', Some("aA".to_string())),
+        ('A', None),
+        ('B', Some("bB".to_string())),
+        ('B', None),
+    ];
+
+    let grammar = LlkGrammar::new(
+        term_symbols,
+        nterm_symbols,
+        start_symbol,
+        lookahead,
+        productions,
+    )
+    .unwrap();
+
+    let parser = LlkParser::new(grammar);
+
+    /* Complete sentences are accepted, remaining nullable symbols are expanded */
+    assert!(parser.parse("aaac").is_ok());
+    assert!(parser.parse("ac").is_ok());
+    assert!(parser.parse("b").is_ok());
+    assert!(parser.parse("bbb").is_ok());
+
+    /* Prefixes of valid sentences are rejected */
+    assert!(matches!(
+        parser.parse(""),
+        Err(LlkError::UnexpectedEndOfInput { position: 0, .. })
+    ));
+    assert!(matches!(
+        parser.parse("aa"),
+        Err(LlkError::UnexpectedEndOfInput { position: 2, .. })
+    ));
+
+    /* Trailing input after a complete sentence is rejected */
+    assert!(matches!(
+        parser.parse("acc"),
+        Err(LlkError::UnexpectedSymbol {
+            symbol: 'c',
+            position: 2,
+            ..
+        })
+    ));
+    assert!(matches!(
+        parser.parse("bba"),
+        Err(LlkError::UnexpectedSymbol {
+            symbol: 'a',
+            position: 2,
+            ..
+        })
+    ));
+
+    let error = parser.parse("aa").err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "llk: unexpected end of input at position 2, expected one of 'a', 'c'"
+    );
+}
+
+#[test]
+fn recursive_start_symbol_test() {
+    let grammar = LlkGrammar::new(
+        vec!['a', 'b'].drain(..).collect(),
+        vec!['S'].drain(..).collect(),
+        'S',
+        2,
+        vec![('S', Some("aSb".to_string())), ('S', None)],
+    )
+    .unwrap();
+
+    let parser = LlkParser::new(grammar);
+
+    /* Only the outermost start symbol is followed by the end of input */
+    assert!(parser.recognize(""));
+    assert!(parser.recognize("ab"));
+    assert!(parser.recognize("aaabbb"));
+    assert!(!parser.recognize("aab"));
+    assert!(!parser.recognize("abb"));
+    assert_eq!(parser.parse("aabb").unwrap().source_text(), "aabb");
+
+    let grammar = LlkGrammar::new(
+        vec!['a'].drain(..).collect(),
+        vec!['S'].drain(..).collect(),
+        'S',
+        1,
+        vec![('S', Some("aS".to_string())), ('S', None)],
+    )
+    .unwrap();
+
+    let parser = LlkParser::new(grammar).with_explicit_nodes();
+    assert!(parser.recognize("a"));
+
+    /* End marker is the last child of the root, not of every start symbol node */
+    let tree = parser.parse("aa").unwrap();
+    let eof_nodes: Vec<_> = tree.preorder().filter(|node| node.is_eof()).collect();
+    assert_eq!(eof_nodes.len(), 1);
+    assert_eq!(tree.children(tree.root()).last(), Some(&eof_nodes[0].id()));
+    assert_eq!(tree.preorder().filter(|node| node.is_epsilon()).count(), 1);
+    assert!(parser.grammar.validate_tree(&tree).is_empty());
+}
+
+#[test]
+fn multibyte_parsing_test() {
+    let term_symbols = vec!['а', 'б', '∑', '√'].drain(..).collect();
+    let nterm_symbols = vec!['С', 'Α', 'Β'].drain(..).collect();
+    let start_symbol = 'С';
+    let lookahead = 2;
+    let productions = vec![
+        ('С', Some("Α∑Β".to_string())),
+        ('Α', Some("аΑ".to_string())),
+        ('Α', Some("а".to_string())),
+        ('Β', Some("√б".to_string())),
+        ('Β', None),
+    ];
+
+    let grammar = LlkGrammar::new(
+        term_symbols,
+        nterm_symbols,
+        start_symbol,
+        lookahead,
+        productions,
+    )
+    .unwrap();
+
+    let parser = LlkParser::new(grammar);
+
+    let tree = parser.parse("ааа∑√б").unwrap();
+    let terms: String = tree
+        .iter()
+        .filter_map(|node| node.symbol())
+        .filter(|c| parser.grammar.is_term(*c))
+        .collect();
+    assert_eq!(terms, "ааа∑√б");
+
+    assert!(parser.parse("а∑").is_ok());
+
+    /* Error positions are counted in symbols */
+    assert!(matches!(
+        parser.parse("аа∑б"),
+        Err(LlkError::UnexpectedSymbol {
+            symbol: 'б',
+            position: 3,
+            ..
+        })
+    ));
+    assert!(matches!(
+        parser.parse("аа∑√"),
+        Err(LlkError::UnexpectedSymbol {
+            symbol: '√',
+            position: 3,
+            ..
+        })
+    ));
+    assert!(matches!(
+        parser.parse(""),
+        Err(LlkError::UnexpectedEndOfInput { position: 0, .. })
+    ));
+    assert!(matches!(
+        parser.parse("аx"),
+        Err(LlkError::UnknownSymbol('x'))
+    ));
+}
+
+#[test]
+fn char_class_parsing_test() {
+    use crate::grammar::char_class::LlkCharClass;
+
+    /* Identifiers and unsigned integers */
+    let term_symbols = vec!['a', 'd'].drain(..).collect();
+    let nterm_symbols = vec!['S', 'I', 'J', 'N', 'M'].drain(..).collect();
+    let start_symbol = 'S';
+    let lookahead = 1;
+    let productions = vec![
+        ('S', Some("I".to_string())),
+        ('S', Some("N".to_string())),
+        ('I', Some("aJ".to_string())),
+        ('J', Some("aJ".to_string())),
+        ('J', Some("dJ".to_string())),
+        ('J', None),
+        ('N', Some("dM".to_string())),
+        ('M', Some("dM".to_string())),
+        ('M', None),
+    ];
+
+    let grammar = LlkGrammar::new(
+        term_symbols,
+        nterm_symbols,
+        start_symbol,
+        lookahead,
+        productions,
+    )
+    .unwrap()
+    .with_char_classes(
+        vec![
+            ('a', LlkCharClass::from_pattern("[a-zA-Z_]").unwrap()),
+            ('d', LlkCharClass::from_pattern("[0-9]").unwrap()),
+        ]
+        .drain(..)
+        .collect(),
+    )
+    .unwrap();
+
+    let parser = LlkParser::new(grammar);
+    let leaves = |tree: &LlkTree| -> String {
+        tree.iter()
+            .filter_map(|node| node.symbol())
+            .filter(|c| !parser.grammar.is_nterm(*c))
+            .collect()
+    };
+
+    /* Leaves record matched characters */
+    let tree = parser.parse("foo_42").unwrap();
+    assert_eq!(leaves(&tree), "foo_42");
+
+    let tree = parser.parse("1024").unwrap();
+    assert_eq!(leaves(&tree), "1024");
+
+    assert!(matches!(
+        parser.parse("4a"),
+        Err(LlkError::UnexpectedSymbol {
+            symbol: 'a',
+            position: 1,
+            ..
+        })
+    ));
+    assert!(matches!(
+        parser.parse("a-b"),
+        Err(LlkError::UnknownSymbol('-'))
+    ));
+
+    /* Class symbols are matched by their classes, not literally */
+    assert!(parser.parse("d").is_ok());
+    assert!(matches!(
+        parser.parse("5d"),
+        Err(LlkError::UnexpectedSymbol { symbol: 'd', .. })
+    ));
+}
+
+#[test]
+fn skip_rules_parsing_test() {
+    use crate::grammar::char_class::LlkCharClass;
+    use crate::grammar::skip_rule::LlkSkipRule;
+
+    /* Comma separated list in brackets */
+    let term_symbols = vec!['[', ']', ',', 'a'].drain(..).collect();
+    let nterm_symbols = vec!['S', 'L', 'R'].drain(..).collect();
+    let start_symbol = 'S';
+    let lookahead = 1;
+    let productions = vec![
+        ('S', Some("[L]".to_string())),
+        ('L', Some("aR".to_string())),
+        ('R', Some(",aR".to_string())),
+        ('R', None),
+    ];
+
+    let grammar = LlkGrammar::new(
+        term_symbols,
+        nterm_symbols,
+        start_symbol,
+        lookahead,
+        productions,
+    )
+    .unwrap()
+    .with_skip_rules(vec![
+        LlkSkipRule::Class(LlkCharClass::from_pattern("[ \t\n]").unwrap()),
+        LlkSkipRule::Delimited("/*".to_string(), "*/".to_string()),
+    ])
+    .unwrap();
+
+    let source = " [a, /* second */ a,\n\ta ] /* end */\n";
+    let parser = LlkParser::new(grammar);
+
+    let tree = parser.parse(source).unwrap();
+    assert_eq!(tree.source_text(), "[a,a,a]");
+
+    /* Error positions refer to the original input */
+    assert!(matches!(
+        parser.parse("[a  a]"),
+        Err(LlkError::UnexpectedSymbol {
+            symbol: 'a',
+            position: 4,
+            ..
+        })
+    ));
+
+    /* Trivia attached to leaves reconstructs the input */
+    let parser = parser.with_trivia();
+
+    let tree = parser.parse(source).unwrap();
+    assert_eq!(tree.source_text(), source);
+    assert!(parser.recognize(source));
+
+    let (tree, errors) = parser.parse_with_recovery(" [a a , a] ");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(tree.source_text(), " [a a , a] ");
+
+    /* Trivia of a sentence without leaves is kept by the tree */
+    let grammar = LlkGrammar::new(
+        vec!['a'].drain(..).collect(),
+        vec!['S'].drain(..).collect(),
+        'S',
+        1,
+        vec![('S', Some("aS".to_string())), ('S', None)],
+    )
+    .unwrap()
+    .with_skip_rules(vec![LlkSkipRule::Class(
+        LlkCharClass::from_pattern("[ ]").unwrap(),
+    )])
+    .unwrap();
+    let parser = LlkParser::new(grammar).with_trivia();
+
+    let tree = parser.parse("  ").unwrap();
+    assert_eq!(tree.source_text(), "  ");
+    assert_eq!(
+        LlkTree::from_json(&tree.to_json()).unwrap().source_text(),
+        "  "
+    );
+    assert_eq!(
+        LlkTree::from_sexpr(&tree.to_sexpr()).unwrap().source_text(),
+        "  "
+    );
+}
+
+#[test]
+fn case_insensitive_parsing_test() {
+    /* Keywords "if" and "in" followed by a name */
+    let term_symbols = vec!['i', 'f', 'n', 'ж'].drain(..).collect();
+    let nterm_symbols = vec!['S', 'K'].drain(..).collect();
+    let start_symbol = 'S';
+    let lookahead = 2;
+    let productions = vec![
+        ('S', Some("Kж".to_string())),
+        ('K', Some("if".to_string())),
+        ('K', Some("in".to_string())),
+    ];
+
+    let grammar = LlkGrammar::new(
+        term_symbols,
+        nterm_symbols,
+        start_symbol,
+        lookahead,
+        productions,
+    )
+    .unwrap()
+    .with_case_insensitive()
+    .unwrap();
+
+    let parser = LlkParser::new(grammar);
+
+    /* Leaves keep the original input symbols */
+    for source in &["ifж", "IFЖ", "iNж", "InЖ"] {
+        let tree = parser.parse(source).unwrap();
+        assert_eq!(tree.source_text(), *source);
+    }
+
+    assert!(matches!(
+        parser.parse("IXж"),
+        Err(LlkError::UnknownSymbol('X'))
+    ));
+    assert!(matches!(
+        parser.parse("NIж"),
+        Err(LlkError::UnexpectedSymbol {
+            symbol: 'N',
+            position: 0,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn parse_as_test() {
+    use crate::grammar::expression_grammar;
+
+    let grammar = expression_grammar();
+
+    let parser = LlkParser::new(grammar);
+
+    let tree = parser.parse_as('T', "(a+a)").unwrap();
+    assert_eq!(tree.source_text(), "(a+a)");
+    assert_eq!(tree.symbol(tree.root()), Some('T'));
+
+    let tree = parser.parse_as('R', "+a+(a)").unwrap();
+    assert_eq!(tree.source_text(), "+a+(a)");
+
+    /* Empty fragment derived through an epsilon production */
+    assert_eq!(parser.parse_as('R', "").unwrap().source_text(), "");
+
+    assert!(parser.parse_as('S', "a+a").is_ok());
+
+    /* Term fragment can not be followed by the rest of an expression */
+    assert!(matches!(
+        parser.parse_as('T', "a+a").err().unwrap(),
+        LlkError::UnexpectedSymbol {
+            symbol: '+',
+            position: 1,
+            ..
+        }
+    ));
+    assert!(matches!(
+        parser.parse_as('T', "(a").err().unwrap(),
+        LlkError::UnexpectedEndOfInput { position: 2, .. }
+    ));
+    assert!(matches!(
+        parser.parse_as('a', "a").err().unwrap(),
+        LlkError::IllegalOperation(_)
+    ));
+
+    /* Fragment is followed by the end of input instead of the rest of a sentence */
+    let grammar = LlkGrammar::new(
+        vec!['a'].drain(..).collect(),
+        vec!['S', 'A'].drain(..).collect(),
+        'S',
+        2,
+        vec![
+            ('S', Some("Aa".to_string())),
+            ('A', Some("a".to_string())),
+            ('A', None),
+        ],
+    )
+    .unwrap();
+
+    let parser = LlkParser::new(grammar);
+    assert!(parser.parse("aa").is_ok());
+    assert_eq!(parser.parse_as('A', "a").unwrap().source_text(), "a");
+    assert_eq!(parser.parse_as('A', "").unwrap().source_text(), "");
+    assert!(parser.parse_as('A', "aa").is_err());
+
+    /* Fragments recover from errors like sentences do */
+    let parser = LlkParser::new(expression_grammar());
+    let (tree, errors) = parser.parse_as_with_recovery('T', "(a+)").unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(tree.symbol(tree.root()), Some('T'));
+    assert_eq!(tree.source_text(), "(a+)");
+    assert!(matches!(
+        parser.parse_as_with_recovery('a', "a").err().unwrap(),
+        LlkError::IllegalOperation(_)
+    ));
+
+    /* Lookup tables of entry symbols are built lazily without locking out other threads */
+    fn assert_sync<T: Sync>(_: &T) {}
+    assert_sync(&parser);
+}
+
+#[test]
+fn parse_prefix_test() {
+    use crate::grammar::char_class::LlkCharClass;
+    use crate::grammar::expression_grammar;
+    use crate::grammar::skip_rule::LlkSkipRule;
+
+    let grammar = expression_grammar()
+        .with_skip_rules(vec![LlkSkipRule::Class(
+            LlkCharClass::from_pattern("[ ]").unwrap(),
+        )])
+        .unwrap();
+
+    let parser = LlkParser::new(grammar);
+
+    let (tree, consumed) = parser.parse_prefix("a+(a)").unwrap();
+    assert_eq!((tree.source_text().as_str(), consumed), ("a+(a)", 5));
+
+    let (tree, consumed) = parser.parse_prefix("a + a ) + a").unwrap();
+    assert_eq!((tree.source_text().as_str(), consumed), ("a+a", 6));
+
+    /* Trailing data may contain symbols unknown to the grammar */
+    let (tree, consumed) = parser.parse_prefix("(a)+a;b").unwrap();
+    assert_eq!((tree.source_text().as_str(), consumed), ("(a)+a", 5));
+
+    /* Incomplete operation is not a part of the prefix */
+    let (tree, consumed) = parser.parse_prefix("a+a+").unwrap();
+    assert_eq!((tree.source_text().as_str(), consumed), ("a+a", 3));
+
+    assert!(matches!(
+        parser.parse_prefix("(a").err().unwrap(),
+        LlkError::UnexpectedEndOfInput { position: 2, .. }
+    ));
+
+    /* Optional tail is dropped when the lookahead rejects it */
+    let new_grammar = || {
+        LlkGrammar::new(
+            vec!['a', 'b'].drain(..).collect(),
+            vec!['S', 'A'].drain(..).collect(),
+            'S',
+            2,
+            vec![
+                ('S', Some("aA".to_string())),
+                ('A', Some("ab".to_string())),
+                ('A', None),
+            ],
+        )
+        .unwrap()
+    };
+
+    let parser = LlkParser::new(new_grammar());
+    let (tree, consumed) = parser.parse_prefix("aaa").unwrap();
+    assert_eq!((tree.source_text().as_str(), consumed), ("a", 1));
+
+    /* Symbols left on the stack at the accepting position are derived to ε */
+    let parser = parser.with_explicit_nodes();
+    let (tree, _) = parser.parse_prefix("aaa").unwrap();
+    assert_eq!(tree.preorder().filter(|node| node.is_epsilon()).count(), 1);
+    assert!(tree.validate(&new_grammar()).is_ok());
+
+    /* Every position of a right recursive sentence accepts */
+    let grammar = LlkGrammar::new(
+        vec!['a', 'b'].drain(..).collect(),
+        vec!['S'].drain(..).collect(),
+        'S',
+        1,
+        vec![('S', Some("aS".to_string())), ('S', None)],
+    )
+    .unwrap();
+
+    let parser = LlkParser::new(grammar);
+    let source = format!("{}b", "a".repeat(10000));
+    let (tree, consumed) = parser.parse_prefix(&source).unwrap();
+    assert_eq!(consumed, 10000);
+    assert_eq!(tree.source_text(), &source[..10000]);
+}
+
+#[test]
+fn completion_test() {
+    use crate::grammar::expression_grammar;
+
+    let grammar = expression_grammar();
+
+    let parser = LlkParser::new(grammar);
+
+    let completion = parser.complete("");
+    assert!(completion.is_viable_prefix() && !completion.can_end());
+    assert_eq!(completion.next_terminals(), &['(', 'a']);
+    assert_eq!(completion.open_nterms(), &['S']);
+
+    let completion = parser.complete("a+");
+    assert_eq!(completion.next_terminals(), &['(', 'a']);
+    assert_eq!(completion.open_nterms(), &['S', 'E', 'R', 'T']);
+
+    let completion = parser.complete("(a");
+    assert_eq!(completion.next_terminals(), &[')', '+']);
+    assert_eq!(completion.open_nterms(), &['S', 'E', 'T', 'E', 'R']);
+    assert!(!completion.can_end());
+
+    let completion = parser.complete("(a)");
+    assert_eq!(completion.next_terminals(), &['+']);
+    assert_eq!(completion.next_lookaheads(), &["\0", "+"]);
+    assert!(completion.can_end());
+
+    assert!(parser.is_viable_prefix("(a+(a"));
+    assert!(!parser.is_viable_prefix("a)"));
+    assert!(!parser.is_viable_prefix("a+x"));
+
+    let completion = parser.complete("(a)a");
+    assert!(!completion.is_viable_prefix());
+    assert!(completion.next_terminals().is_empty());
+
+    /* Lookahead strings continue the typed part of the lookahead */
+    let grammar = LlkGrammar::new(
+        vec!['a', 'b'].drain(..).collect(),
+        vec!['S', 'A'].drain(..).collect(),
+        'S',
+        2,
+        vec![
+            ('S', Some("Ab".to_string())),
+            ('A', Some("aA".to_string())),
+            ('A', Some("a".to_string())),
+        ],
+    )
+    .unwrap();
+
+    let parser = LlkParser::new(grammar);
+
+    let completion = parser.complete("a");
+    assert_eq!(completion.next_lookaheads(), &["a", "b"]);
+    assert_eq!(completion.open_nterms(), &['S']);
+
+    let completion = parser.complete("aab");
+    assert_eq!(completion.next_lookaheads(), &["\0"]);
+    assert!(completion.next_terminals().is_empty() && completion.can_end());
+    assert!(completion.open_nterms().is_empty());
+
+    /* Terminal symbol on the stack top is suggested along with the symbols following it */
+    let grammar = LlkGrammar::new(
+        vec!['a', 'b', 'c'].drain(..).collect(),
+        vec!['S', 'A'].drain(..).collect(),
+        'S',
+        2,
+        vec![
+            ('S', Some("abcA".to_string())),
+            ('A', Some("c".to_string())),
+            ('A', None),
+        ],
+    )
+    .unwrap();
+
+    let parser = LlkParser::new(grammar);
+
+    let completion = parser.complete("ab");
+    assert_eq!(completion.next_terminals(), &['c']);
+    assert_eq!(completion.next_lookaheads(), &["c\0", "cc"]);
+    assert_eq!(completion.open_nterms(), &['S']);
+}
+
+#[test]
+fn listener_test() {
+    use crate::grammar::expression_grammar;
+    use crate::grammar::span::LlkSpan;
+
+    let grammar = expression_grammar();
+
+    /* Nesting depth of parentheses computed without building the tree */
+    #[derive(Default)]
+    struct DepthListener {
+        events: String,
+        open_terms: usize,
+        max_depth: usize,
+        errors: usize,
+    }
+
+    impl LlkParseListener for DepthListener {
+        fn enter_nonterminal(&mut self, nterm: char, _production: Option<usize>) {
+            self.events.push(nterm);
+            if nterm == 'T' {
+                self.open_terms += 1;
+                self.max_depth = std::cmp::max(self.max_depth, self.open_terms);
+            }
+        }
+
+        fn exit_nonterminal(&mut self, nterm: char) {
+            self.events.push('/');
+            if nterm == 'T' {
+                self.open_terms -= 1;
+            }
+        }
+
+        fn terminal(&mut self, symbol: char, _span: LlkSpan, _leading_trivia: &str) {
+            self.events.push(symbol);
+        }
+
+        fn error(&mut self, _error: &LlkError, _skipped: &str, _span: LlkSpan) {
+            self.errors += 1;
+        }
+    }
+
+    let parser = LlkParser::new(grammar);
+
+    let mut listener = DepthListener::default();
+    parser.parse_with_listener("a+(a)", &mut listener).unwrap();
+    assert_eq!(listener.events, "SETa/R+T(ETa/R//)/R////");
+    assert_eq!((listener.max_depth, listener.open_terms), (2, 0));
+    assert_eq!(listener.errors, 0);
+
+    let mut listener = DepthListener::default();
+    assert!(parser.parse_with_listener("((a)", &mut listener).is_err());
+    assert_eq!((listener.max_depth, listener.errors), (3, 1));
+
+    /* Tree builder is just another listener */
+    let mut tree_builder = LlkTreeBuilder::new();
+    parser
+        .parse_with_listener("(a)+a", &mut tree_builder)
+        .unwrap();
+    let tree = tree_builder.into_tree().unwrap();
+    assert_eq!(tree.source_text(), "(a)+a");
+    assert_eq!(tree.to_sexpr(), parser.parse("(a)+a").unwrap().to_sexpr());
+}
+
+#[test]
+fn recognize_test() {
+    use crate::grammar::expression_grammar;
+
+    let grammar = expression_grammar();
+
+    let parser = LlkParser::new(grammar);
+
+    assert!(parser.recognize("a+(a+a)"));
+    assert!(!parser.recognize("a+(a+a"));
+    assert!(!parser.recognize("a+b"));
+    assert!(!parser.recognize(""));
+
+    assert!(parser.recognize_with_error("(a)").is_ok());
+    assert!(matches!(
+        parser.recognize_with_error("a+(a+a").err().unwrap(),
+        LlkError::UnexpectedEndOfInput { position: 6, .. }
+    ));
+    assert!(matches!(
+        parser.recognize_with_error("a)").err().unwrap(),
+        LlkError::UnexpectedSymbol {
+            symbol: ')',
+            position: 1,
+            ..
+        }
+    ));
+}
@@ -0,0 +1,90 @@
+use crate::grammar::span::{LlkLocation, LlkSpan};
+use crate::grammar::LlkGrammar;
+
+/*
+ * Input string split into symbols significant for the grammar and
+ * skipped fragments (trivia) preceding each of them.
+ * The last symbol is always the end of input marker.
+ * Trivia is left empty when the parser does not keep it
+ */
+pub(super) struct LlkInput {
+    pub(super) symbols: Vec<char>,
+    pub(super) locations: Vec<LlkLocation>,
+    pub(super) trivia: Vec<String>,
+}
+
+impl LlkInput {
+    pub(super) fn scan(grammar: &LlkGrammar, string: &str, keep_trivia: bool) -> LlkInput {
+        let chars: Vec<char> = string.chars().collect();
+        let mut input = LlkInput {
+            symbols: Vec::with_capacity(chars.len() + 1),
+            locations: Vec::with_capacity(chars.len() + 1),
+            trivia: Vec::with_capacity(chars.len() + 1),
+        };
+        let mut trivia = String::default();
+        let mut location = LlkLocation::default();
+
+        while location.position < chars.len() {
+            let position = location.position;
+
+            if let Some(skip_len) = grammar.match_skip(&chars[position..]) {
+                for symbol in &chars[position..position + skip_len] {
+                    if keep_trivia {
+                        trivia.push(*symbol);
+                    }
+                    location = location.advance(*symbol);
+                }
+            } else {
+                input.push(chars[position], location, std::mem::take(&mut trivia));
+                location = location.advance(chars[position]);
+            }
+        }
+
+        input.push(LlkGrammar::EOF, location, trivia);
+        input
+    }
+
+    pub(super) fn get_text(&self, start: usize, end: usize) -> String {
+        let mut text = String::default();
+
+        for i in start..end {
+            text.push_str(&self.trivia[i]);
+            text.push(self.symbols[i]);
+        }
+
+        text
+    }
+
+    /* Span of the symbols from start to end, trivia excluded */
+    pub(super) fn get_span(&self, start: usize, end: usize) -> LlkSpan {
+        let end_location = if end > start {
+            self.locations[end - 1].advance(self.symbols[end - 1])
+        } else {
+            self.locations[start]
+        };
+
+        LlkSpan::new(self.locations[start], end_location)
+    }
+
+    /* Input consisting of the first len symbols followed by the end of input */
+    pub(super) fn truncate(&self, len: usize) -> LlkInput {
+        let mut symbols = self.symbols[..len].to_vec();
+        symbols.push(LlkGrammar::EOF);
+
+        LlkInput {
+            symbols,
+            locations: self.locations[..=len].to_vec(),
+            trivia: self.trivia[..=len].to_vec(),
+        }
+    }
+
+    pub(super) fn get_term_string(&self) -> String {
+        self.symbols[..self.symbols.len() - 1].iter().collect()
+    }
+
+    fn push(&mut self, symbol: char, location: LlkLocation, trivia: String) {
+        self.symbols.push(symbol);
+        self.locations.push(location);
+        self.trivia.push(trivia);
+    }
+}
@@ -0,0 +1,537 @@
+use std::collections::VecDeque;
+use std::io::Read;
+
+use super::{LlkParser, LlkStackItem};
+use crate::error::LlkError;
+use crate::grammar::listener::LlkParseListener;
+use crate::grammar::skip_rule::LlkSkipRule;
+use crate::grammar::span::{LlkLocation, LlkSpan};
+use crate::grammar::tree::{LlkTree, LlkTreeBuilder};
+use crate::grammar::LlkGrammar;
+
+/* Significant input symbol together with the skipped fragment preceding it */
+struct LlkToken {
+    symbol: char,
+    location: LlkLocation,
+    trivia: String,
+}
+
+enum LlkSkipState {
+    Idle,
+    Class(usize),
+    Delimited(Vec<char>),
+}
+
+/*
+ * Scanner producing tokens from the input as it arrives.
+ * Only symbols which may still turn out to be a part of a skipped
+ * fragment are buffered, so tokens are produced exactly like
+ * the whole input was scanned at once
+ */
+struct LlkStreamScanner {
+    chars: VecDeque<char>,
+    location: LlkLocation,
+    trivia: String,
+    keep_trivia: bool,
+    skip_state: LlkSkipState,
+    closed: bool,
+}
+
+impl LlkStreamScanner {
+    fn new(keep_trivia: bool) -> LlkStreamScanner {
+        LlkStreamScanner {
+            chars: VecDeque::new(),
+            location: LlkLocation::default(),
+            trivia: String::default(),
+            keep_trivia,
+            skip_state: LlkSkipState::Idle,
+            closed: false,
+        }
+    }
+
+    fn push(&mut self, symbol: char) {
+        self.chars.push_back(symbol);
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+    }
+
+    /* None means that more input is needed to produce the next token */
+    fn next_token(&mut self, grammar: &LlkGrammar) -> Option<LlkToken> {
+        loop {
+            match &self.skip_state {
+                LlkSkipState::Class(rule_index) => {
+                    if let LlkSkipRule::Class(char_class) = &grammar.skip_rules[*rule_index] {
+                        while self.chars.front().is_some_and(|c| char_class.contains(*c)) {
+                            self.skip(1);
+                        }
+                    }
+
+                    if self.chars.is_empty() && !self.closed {
+                        return None;
+                    }
+                    self.skip_state = LlkSkipState::Idle;
+                }
+                LlkSkipState::Delimited(close) => {
+                    let close = close.clone();
+
+                    loop {
+                        if self.starts_with(&close) {
+                            self.skip(close.len());
+                            break;
+                        }
+                        if self.chars.len() < close.len() && !self.closed {
+                            return None;
+                        }
+                        if self.chars.is_empty() {
+                            /* Fragment is not closed till the end of input */
+                            break;
+                        }
+                        self.skip(1);
+                    }
+
+                    self.skip_state = LlkSkipState::Idle;
+                }
+                LlkSkipState::Idle => {
+                    let symbol = if let Some(symbol) = self.chars.front() {
+                        *symbol
+                    } else if self.closed {
+                        return Some(self.take_token(LlkGrammar::EOF));
+                    } else {
+                        return None;
+                    };
+
+                    /* Skip rules are tried in order, the first matching one wins */
+                    for (rule_index, skip_rule) in grammar.skip_rules.iter().enumerate() {
+                        match skip_rule {
+                            LlkSkipRule::Class(char_class) => {
+                                if char_class.contains(symbol) {
+                                    self.skip_state = LlkSkipState::Class(rule_index);
+                                    break;
+                                }
+                            }
+                            LlkSkipRule::Delimited(open, close) => {
+                                let open: Vec<char> = open.chars().collect();
+
+                                if self.starts_with(&open) {
+                                    self.skip(open.len());
+                                    self.skip_state =
+                                        LlkSkipState::Delimited(close.chars().collect());
+                                    break;
+                                }
+                                if !self.closed
+                                    && self.chars.len() < open.len()
+                                    && self.chars.iter().zip(&open).all(|(a, b)| a == b)
+                                {
+                                    return None;
+                                }
+                            }
+                        }
+                    }
+
+                    if let LlkSkipState::Idle = self.skip_state {
+                        self.chars.pop_front();
+                        let token = self.take_token(symbol);
+                        self.location = self.location.advance(symbol);
+                        return Some(token);
+                    }
+                }
+            }
+        }
+    }
+
+    fn starts_with(&self, string: &[char]) -> bool {
+        self.chars.len() >= string.len() && self.chars.iter().zip(string).all(|(a, b)| a == b)
+    }
+
+    fn skip(&mut self, len: usize) {
+        for symbol in self.chars.drain(..len) {
+            if self.keep_trivia {
+                self.trivia.push(symbol);
+            }
+            self.location = self.location.advance(symbol);
+        }
+    }
+
+    fn take_token(&mut self, symbol: char) -> LlkToken {
+        LlkToken {
+            symbol,
+            location: self.location,
+            trivia: std::mem::take(&mut self.trivia),
+        }
+    }
+}
+
+/*
+ * Parser stack machine suspended whenever fewer than k lookahead
+ * symbols are available and resumed when more input is fed.
+ * Input is consumed as soon as it arrives, so only the lookahead
+ * window is kept in memory
+ */
+pub struct LlkParseSession<'a> {
+    parser: &'a LlkParser,
+    scanner: LlkStreamScanner,
+    lookahead: VecDeque<LlkToken>,
+    stack: Vec<LlkStackItem>,
+    tree_builder: LlkTreeBuilder,
+    error: Option<LlkError>,
+}
+
+impl<'a> LlkParseSession<'a> {
+    pub(super) fn new(parser: &'a LlkParser) -> LlkParseSession<'a> {
+        let start_symbol = parser.grammar.start_symbol;
+
+        LlkParseSession {
+            parser,
+            scanner: LlkStreamScanner::new(parser.keep_trivia),
+            lookahead: VecDeque::with_capacity(parser.grammar.lookahead),
+            stack: vec![
+                LlkStackItem::Symbol(LlkGrammar::EOF),
+                LlkStackItem::Symbol(start_symbol),
+            ],
+            tree_builder: parser.create_tree_builder(start_symbol),
+            error: None,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        for symbol in chunk.chars() {
+            self.push_char(symbol);
+        }
+    }
+
+    pub(super) fn push_char(&mut self, symbol: char) {
+        if self.error.is_none() {
+            self.scanner.push(symbol);
+            self.advance();
+        }
+    }
+
+    /* Once failed, session ignores the rest of the input */
+    pub fn has_failed(&self) -> bool {
+        self.error.is_some()
+    }
+
+    pub fn finish(mut self) -> Result<LlkTree, LlkError> {
+        self.scanner.close();
+        self.advance();
+
+        if let Some(error) = self.error {
+            Err(error)
+        } else {
+            Ok(self
+                .tree_builder
+                .into_tree()
+                .expect("LlkParseSession fatal error: finished without tree"))
+        }
+    }
+
+    fn advance(&mut self) {
+        let grammar = &self.parser.grammar;
+
+        while let Some(&item) = self.stack.last() {
+            if self.error.is_some() {
+                return;
+            }
+
+            let top = match item {
+                LlkStackItem::Symbol(symbol) => symbol,
+                LlkStackItem::Exit(nterm) => {
+                    self.stack.pop();
+                    self.tree_builder.exit_nonterminal(nterm);
+                    continue;
+                }
+            };
+
+            /* Fill the lookahead window, suspend if the input is not there yet */
+            while self.lookahead.len() < grammar.lookahead
+                && self
+                    .lookahead
+                    .back()
+                    .map_or(true, |token| token.symbol != LlkGrammar::EOF)
+            {
+                match self.scanner.next_token(grammar) {
+                    Some(token) => {
+                        if token.symbol != LlkGrammar::EOF {
+                            if let Err(error) = crate::grammar::grammar_assert::assert_term_string(
+                                grammar,
+                                &token.symbol.to_string(),
+                            ) {
+                                self.error = Some(error);
+                                return;
+                            }
+                        }
+                        self.lookahead.push_back(token);
+                    }
+                    None => return,
+                }
+            }
+
+            let current = &self.lookahead[0];
+
+            if grammar.is_term(top) {
+                if !grammar.term_matches(top, current.symbol) {
+                    self.error = Some(LlkParser::unexpected_symbol_error(
+                        current.symbol,
+                        current.location.position,
+                        vec![top.to_string()],
+                    ));
+                    return;
+                }
+
+                self.stack.pop();
+                let token = self.lookahead.pop_front().unwrap();
+                let trivia = if self.parser.keep_trivia {
+                    token.trivia
+                } else {
+                    String::default()
+                };
+
+                if top != LlkGrammar::EOF {
+                    let span = LlkSpan::new(token.location, token.location.advance(token.symbol));
+                    self.tree_builder.terminal(token.symbol, span, &trivia);
+                } else {
+                    self.tree_builder.end_of_input(token.location, &trivia);
+                }
+            } else {
+                let lookahead: String = self.lookahead.iter().map(|token| token.symbol).collect();
+
+                if let Some(production) =
+                    self.parser
+                        .find_production(&self.parser.lut, top, &lookahead)
+                {
+                    self.stack.pop();
+                    self.tree_builder.enter_nonterminal(top, Some(production));
+
+                    self.stack.push(LlkStackItem::Exit(top));
+                    self.stack.extend(
+                        self.parser
+                            .get_production_rhs(production)
+                            .chars()
+                            .rev()
+                            .map(LlkStackItem::Symbol),
+                    );
+                } else {
+                    self.error = Some(LlkParser::unexpected_symbol_error(
+                        current.symbol,
+                        current.location.position,
+                        self.parser.get_expected(&self.parser.lut, top),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/* Decode UTF-8 input in fixed size chunks, sequences split between chunks are carried over */
+pub(super) fn read_utf8<R: Read>(
+    mut reader: R,
+    session: &mut LlkParseSession,
+) -> Result<(), LlkError> {
+    const BUFFER_SIZE: usize = 4096;
+
+    let mut buffer = [0u8; BUFFER_SIZE];
+    let mut pending = 0;
+    let mut offset = 0;
+
+    loop {
+        let read = match reader.read(&mut buffer[pending..]) {
+            Ok(read) => read,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(LlkError::InputReadFailed(error.to_string())),
+        };
+
+        if read == 0 {
+            break;
+        }
+
+        let len = pending + read;
+        let valid_len = match std::str::from_utf8(&buffer[..len]) {
+            Ok(string) => {
+                session.feed(string);
+                len
+            }
+            Err(error) => {
+                let valid_len = error.valid_up_to();
+                session.feed(std::str::from_utf8(&buffer[..valid_len]).unwrap());
+
+                if error.error_len().is_some() {
+                    return Err(LlkError::InputReadFailed(format!(
+                        "invalid UTF-8 sequence at byte {}",
+                        offset + valid_len
+                    )));
+                }
+                valid_len
+            }
+        };
+
+        buffer.copy_within(valid_len..len, 0);
+        pending = len - valid_len;
+        offset += valid_len;
+
+        if session.has_failed() {
+            return Ok(());
+        }
+    }
+
+    if pending > 0 {
+        return Err(LlkError::InputReadFailed(format!(
+            "incomplete UTF-8 sequence at byte {}",
+            offset
+        )));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn stream_parsing_test() {
+    use crate::grammar::char_class::LlkCharClass;
+
+    /* Comma separated list in brackets */
+    let term_symbols = vec!['[', ']', ',', 'a'].drain(..).collect();
+    let nterm_symbols = vec!['S', 'L', 'R'].drain(..).collect();
+    let start_symbol = 'S';
+    let lookahead = 2;
+    let productions = vec![
+        ('S', Some("[L]".to_string())),
+        ('L', Some("aR".to_string())),
+        ('R', Some(",aR".to_string())),
+        ('R', None),
+    ];
+
+    let grammar = LlkGrammar::new(
+        term_symbols,
+        nterm_symbols,
+        start_symbol,
+        lookahead,
+        productions,
+    )
+    .unwrap()
+    .with_skip_rules(vec![
+        LlkSkipRule::Delimited("/*".to_string(), "*/".to_string()),
+        LlkSkipRule::Class(LlkCharClass::from_pattern("[ /\n]").unwrap()),
+    ])
+    .unwrap();
+
+    let parser = LlkParser::new(grammar).with_trivia();
+    let source = " [a,/* ∑ */ a,\n a / ]/* end *";
+
+    let tree = parser.parse_chars(source.chars()).unwrap();
+    assert_eq!(tree.source_text(), source);
+    assert_eq!(
+        tree.source_text(),
+        parser.parse(source).unwrap().source_text()
+    );
+
+    /* Reader returning a single byte at a time splits multibyte symbols */
+    struct ByteReader<'a>(&'a [u8]);
+
+    impl Read for ByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = std::cmp::min(1, self.0.len());
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    let tree = parser.parse_reader(ByteReader(source.as_bytes())).unwrap();
+    assert_eq!(tree.source_text(), source);
+
+    let tree = parser.parse_reader(source.as_bytes()).unwrap();
+    assert_eq!(tree.source_text(), source);
+
+    assert!(matches!(
+        parser.parse_chars("[a, a a]".chars()).err().unwrap(),
+        LlkError::UnexpectedSymbol {
+            symbol: 'a',
+            position: 6,
+            ..
+        }
+    ));
+    assert!(matches!(
+        parser.parse_chars("[a,a".chars()).err().unwrap(),
+        LlkError::UnexpectedEndOfInput { position: 4, .. }
+    ));
+    assert!(matches!(
+        parser.parse_chars("[a, b]".chars()).err().unwrap(),
+        LlkError::UnknownSymbol('b')
+    ));
+    assert!(matches!(
+        parser.parse_reader(&[b'[', 0xff, b']'][..]).err().unwrap(),
+        LlkError::InputReadFailed(_)
+    ));
+    assert!(matches!(
+        parser.parse_reader(&"[a]∑".as_bytes()[..5]).err().unwrap(),
+        LlkError::InputReadFailed(_)
+    ));
+}
+
+#[test]
+fn parse_session_test() {
+    use crate::grammar::char_class::LlkCharClass;
+
+    let term_symbols = vec!['[', ']', ',', 'a'].drain(..).collect();
+    let nterm_symbols = vec!['S', 'L', 'R'].drain(..).collect();
+    let start_symbol = 'S';
+    let lookahead = 2;
+    let productions = vec![
+        ('S', Some("[L]".to_string())),
+        ('L', Some("aR".to_string())),
+        ('R', Some(",aR".to_string())),
+        ('R', None),
+    ];
+
+    let grammar = LlkGrammar::new(
+        term_symbols,
+        nterm_symbols,
+        start_symbol,
+        lookahead,
+        productions,
+    )
+    .unwrap()
+    .with_skip_rules(vec![
+        LlkSkipRule::Delimited("/*".to_string(), "*/".to_string()),
+        LlkSkipRule::Class(LlkCharClass::from_pattern("[ \n]").unwrap()),
+    ])
+    .unwrap();
+
+    let parser = LlkParser::new(grammar).with_trivia();
+
+    /* Chunk boundaries split comment delimiters and the lookahead window */
+    let chunks = ["[a", ",/", "* x *", "/ a", "", ",a ]", " /* y */"];
+    let mut session = parser.session();
+    for chunk in &chunks {
+        session.feed(chunk);
+        assert!(!session.has_failed());
+    }
+    let tree = session.finish().unwrap();
+    assert_eq!(tree.source_text(), chunks.concat());
+
+    /* Errors are detected as soon as the lookahead is available */
+    let mut session = parser.session();
+    session.feed("[a,");
+    assert!(!session.has_failed());
+    session.feed("]");
+    assert!(session.has_failed());
+    session.feed("a]");
+    assert!(matches!(
+        session.finish().err().unwrap(),
+        LlkError::UnexpectedSymbol {
+            symbol: ',',
+            position: 2,
+            ..
+        }
+    ));
+
+    /* Missing input is only an error when the session is finished */
+    let mut session = parser.session();
+    session.feed("[a,a");
+    assert!(!session.has_failed());
+    assert!(matches!(
+        session.finish().err().unwrap(),
+        LlkError::UnexpectedEndOfInput { position: 4, .. }
+    ));
+}
@@ -0,0 +1,62 @@
+use super::char_class::LlkCharClass;
+
+/*
+ * Input fragment silently consumed by the parser between terminal symbols.
+ * Delimited fragment runs from the opening string through the closing one
+ * or through the end of input if the closing string is missing
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LlkSkipRule {
+    Class(LlkCharClass),
+    Delimited(String, String),
+}
+
+impl LlkSkipRule {
+    pub(super) fn match_len(&self, chars: &[char]) -> Option<usize> {
+        match self {
+            LlkSkipRule::Class(char_class) => {
+                let len = chars
+                    .iter()
+                    .take_while(|c| char_class.contains(**c))
+                    .count();
+
+                if len > 0 {
+                    Some(len)
+                } else {
+                    None
+                }
+            }
+            LlkSkipRule::Delimited(open, close) => {
+                let open: Vec<char> = open.chars().collect();
+                let close: Vec<char> = close.chars().collect();
+
+                if !chars.starts_with(&open) {
+                    return None;
+                }
+
+                let body = &chars[open.len()..];
+                let len = (0..body.len())
+                    .find(|i| body[*i..].starts_with(&close))
+                    .map_or(body.len(), |i| i + close.len());
+
+                Some(open.len() + len)
+            }
+        }
+    }
+}
+
+#[test]
+fn skip_rule_match_test() {
+    let spaces = LlkSkipRule::Class(LlkCharClass::from_pattern("[ \t\n]").unwrap());
+    let comment = LlkSkipRule::Delimited("/*".to_string(), "*/".to_string());
+    let line_comment = LlkSkipRule::Delimited("#".to_string(), "\n".to_string());
+    let chars = |s: &str| s.chars().collect::<Vec<char>>();
+
+    assert_eq!(spaces.match_len(&chars(" \t\nx ")), Some(3));
+    assert_eq!(spaces.match_len(&chars("x ")), None);
+    assert_eq!(comment.match_len(&chars("/* a */x")), Some(7));
+    assert_eq!(comment.match_len(&chars("/*/x")), Some(4));
+    assert_eq!(comment.match_len(&chars("/x")), None);
+    assert_eq!(line_comment.match_len(&chars("# a\nx")), Some(4));
+    assert_eq!(line_comment.match_len(&chars("# a")), Some(3));
+}
@@ -0,0 +1,92 @@
+/*
+ * Location in the input. Position is the index of a character like in
+ * errors, offset is in bytes, line and column are counted from 1
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LlkLocation {
+    pub position: usize,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for LlkLocation {
+    fn default() -> LlkLocation {
+        LlkLocation {
+            position: 0,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl LlkLocation {
+    /* Location right after the given character */
+    pub(super) fn advance(self, symbol: char) -> LlkLocation {
+        let (line, column) = if symbol == '\n' {
+            (self.line + 1, 1)
+        } else {
+            (self.line, self.column + 1)
+        };
+
+        LlkLocation {
+            position: self.position + 1,
+            offset: self.offset + symbol.len_utf8(),
+            line,
+            column,
+        }
+    }
+}
+
+/* Fragment of the input between two locations, the end one is exclusive */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LlkSpan {
+    pub start: LlkLocation,
+    pub end: LlkLocation,
+}
+
+impl LlkSpan {
+    pub fn new(start: LlkLocation, end: LlkLocation) -> LlkSpan {
+        LlkSpan { start, end }
+    }
+
+    pub fn empty(location: LlkLocation) -> LlkSpan {
+        LlkSpan::new(location, location)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start.offset == self.end.offset
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.offset - self.start.offset
+    }
+
+    /* Panics if the span does not come from the given input */
+    pub fn text<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.start.offset..self.end.offset]
+    }
+}
+
+#[test]
+fn location_test() {
+    let mut location = LlkLocation::default();
+    for symbol in "ab\nč".chars() {
+        location = location.advance(symbol);
+    }
+
+    assert_eq!(
+        location,
+        LlkLocation {
+            position: 4,
+            offset: 5,
+            line: 2,
+            column: 2
+        }
+    );
+
+    let span = LlkSpan::new(LlkLocation::default(), location);
+    assert_eq!((span.len(), span.text("ab\nčd")), (5, "ab\nč"));
+    assert!(LlkSpan::empty(location).is_empty());
+}
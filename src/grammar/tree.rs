@@ -1,108 +1,694 @@
-pub enum LlkTree {
-    Node(LlkTreeNode),
-    Leaf(char),
+use std::collections::HashMap;
+
+use super::listener::LlkParseListener;
+use super::span::{LlkLocation, LlkSpan};
+use super::LlkGrammar;
+use crate::error::LlkError;
+
+mod dot;
+mod printer;
+mod serialization;
+mod traversal;
+mod visitor;
+pub use printer::{LlkTreeFormat, LlkTreePrinter};
+pub use traversal::{LlkTreeEvent, LlkTreeEvents, LlkTreeIter};
+pub use visitor::{LlkTreeFold, LlkTreeVisitor};
+
+/* Identifier of a node, stable for the lifetime of its tree */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LlkNodeId(usize);
+
+impl LlkNodeId {
+    /* Position of the node in the arena, usable to index side tables of a tree */
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Clone)]
+pub enum LlkNodeKind {
+    Nonterminal(char),
+    Terminal {
+        symbol: char,
+        leading_trivia: String,
+        trailing_trivia: String,
+    },
+    Error(String),
+    /* Only present in trees built with explicit nodes */
+    Epsilon,
+    Eof,
 }
 
 pub struct LlkTreeNode {
-    symbol: char,
-    children: Vec<LlkTree>,
+    id: LlkNodeId,
+    kind: LlkNodeKind,
+    span: LlkSpan,
+    production: Option<usize>,
+    label: Option<String>,
+    parent: Option<LlkNodeId>,
+    children: Vec<LlkNodeId>,
+}
+
+impl LlkTreeNode {
+    pub fn id(&self) -> LlkNodeId {
+        self.id
+    }
+
+    pub fn kind(&self) -> &LlkNodeKind {
+        &self.kind
+    }
+
+    /* Grammar symbol of a non-terminal or a terminal node, the other nodes carry none */
+    pub fn symbol(&self) -> Option<char> {
+        match self.kind {
+            LlkNodeKind::Nonterminal(symbol) | LlkNodeKind::Terminal { symbol, .. } => Some(symbol),
+            LlkNodeKind::Error(_) | LlkNodeKind::Epsilon | LlkNodeKind::Eof => None,
+        }
+    }
+
+    /*
+     * Input covered by the node, trivia excluded. Span of a non-terminal
+     * node is the union of the spans of its children, nodes derived to ε
+     * have an empty span at the start of the following symbol
+     */
+    pub fn span(&self) -> LlkSpan {
+        self.span
+    }
+
+    /* Production expanding a non-terminal node, none for the other nodes and the ones made up by error recovery */
+    pub fn production(&self) -> Option<usize> {
+        self.production
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn parent(&self) -> Option<LlkNodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[LlkNodeId] {
+        &self.children
+    }
+
+    pub fn is_nonterminal(&self) -> bool {
+        matches!(self.kind, LlkNodeKind::Nonterminal(_))
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(self.kind, LlkNodeKind::Terminal { .. })
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self.kind, LlkNodeKind::Error(_))
+    }
+
+    pub fn is_epsilon(&self) -> bool {
+        matches!(self.kind, LlkNodeKind::Epsilon)
+    }
+
+    pub fn is_eof(&self) -> bool {
+        matches!(self.kind, LlkNodeKind::Eof)
+    }
+}
+
+/*
+ * Parse tree stored in an arena. Nodes refer to their parent and
+ * children by identifiers, which index the arena. Trivia at the end of
+ * input is kept by the root when no leaf precedes it
+ */
+pub struct LlkTree {
+    nodes: Vec<LlkTreeNode>,
+    root: LlkNodeId,
+    trailing_trivia: String,
 }
 
 impl LlkTree {
     pub(super) fn new(root_symbol: char) -> LlkTree {
-        LlkTree::Node(LlkTreeNode {
-            symbol: root_symbol,
-            children: Vec::new(),
-        })
+        LlkTree {
+            nodes: vec![LlkTreeNode {
+                id: LlkNodeId(0),
+                kind: LlkNodeKind::Nonterminal(root_symbol),
+                span: LlkSpan::default(),
+                production: None,
+                label: None,
+                parent: None,
+                children: Vec::new(),
+            }],
+            root: LlkNodeId(0),
+            trailing_trivia: String::default(),
+        }
     }
 
-    pub(super) fn push_node(&mut self, symbol: char) -> *mut LlkTree {
-        if let LlkTree::Node(node) = self {
-            let new_node = LlkTree::Node(LlkTreeNode {
-                symbol,
+    /* Tree with a copy of the node as its root and the given trees as its subtrees */
+    pub fn from_node(node: &LlkTreeNode, children: Vec<LlkTree>) -> LlkTree {
+        let mut tree = LlkTree {
+            nodes: vec![LlkTreeNode {
+                id: LlkNodeId(0),
+                kind: node.kind.clone(),
+                span: node.span,
+                production: node.production,
+                label: node.label.clone(),
+                parent: None,
                 children: Vec::new(),
-            });
-            node.children.push(new_node);
-            node.children.last_mut().unwrap() as *mut LlkTree
-        } else {
-            panic!(
-                "LlkTree fatal error:\
-                 unexpected push_node method on LlkTree::Leaf enum item"
-            )
+            }],
+            root: LlkNodeId(0),
+            trailing_trivia: String::default(),
+        };
+
+        for child in children {
+            tree.append_subtree(child);
         }
+
+        tree
+    }
+
+    pub fn root(&self) -> LlkNodeId {
+        self.root
+    }
+
+    /* Panics if the identifier is out of range, e.g. taken from a larger tree */
+    pub fn node(&self, id: LlkNodeId) -> &LlkTreeNode {
+        &self.nodes[id.0]
+    }
+
+    pub fn node_ids(&self) -> impl Iterator<Item = LlkNodeId> {
+        (0..self.nodes.len()).map(LlkNodeId)
+    }
+
+    pub fn symbol(&self, id: LlkNodeId) -> Option<char> {
+        self.node(id).symbol()
+    }
+
+    pub fn span(&self, id: LlkNodeId) -> LlkSpan {
+        self.node(id).span
+    }
+
+    pub fn production(&self, id: LlkNodeId) -> Option<usize> {
+        self.node(id).production
+    }
+
+    pub fn parent(&self, id: LlkNodeId) -> Option<LlkNodeId> {
+        self.node(id).parent
+    }
+
+    pub fn children(&self, id: LlkNodeId) -> &[LlkNodeId] {
+        &self.node(id).children
+    }
+
+    pub fn first_child(&self, id: LlkNodeId) -> Option<LlkNodeId> {
+        self.node(id).children.first().copied()
     }
 
-    pub(super) fn push_leaf(&mut self, symbol: char) {
-        if let LlkTree::Node(node) = self {
-            let new_node = LlkTree::Leaf(symbol);
-            node.children.push(new_node);
-        } else {
-            panic!(
-                "LlkTree fatal error:\
-                 unexpected push_leaf method on LlkTree::Leaf enum item"
-            )
+    pub fn last_child(&self, id: LlkNodeId) -> Option<LlkNodeId> {
+        self.node(id).children.last().copied()
+    }
+
+    pub fn next_sibling(&self, id: LlkNodeId) -> Option<LlkNodeId> {
+        let siblings = self.children(self.parent(id)?);
+        let position = siblings.iter().position(|sibling| *sibling == id)?;
+        siblings.get(position + 1).copied()
+    }
+
+    pub fn prev_sibling(&self, id: LlkNodeId) -> Option<LlkNodeId> {
+        let siblings = self.children(self.parent(id)?);
+        let position = siblings.iter().position(|sibling| *sibling == id)?;
+        position.checked_sub(1).map(|position| siblings[position])
+    }
+
+    /* Nodes on the path from the node up to the root, the node itself excluded */
+    pub fn ancestors(&self, id: LlkNodeId) -> impl Iterator<Item = LlkNodeId> + '_ {
+        std::iter::successors(self.parent(id), move |ancestor| self.parent(*ancestor))
+    }
+
+    pub fn source_text(&self) -> String {
+        let mut text = String::default();
+
+        for node in self.preorder() {
+            match &node.kind {
+                LlkNodeKind::Terminal {
+                    symbol,
+                    leading_trivia,
+                    trailing_trivia,
+                } => {
+                    text.push_str(leading_trivia);
+                    text.push(*symbol);
+                    text.push_str(trailing_trivia);
+                }
+                LlkNodeKind::Error(skipped) => text.push_str(skipped),
+                _ => (),
+            }
         }
+
+        text.push_str(&self.trailing_trivia);
+        text
     }
 
-    pub fn iter(&self) -> LlkTreeIter {
-        let mut iter = LlkTreeIter {
-            unvisited: Vec::new(),
-        };
-        iter.lnr(self, 0);
-        iter
+    /* Productions of the leftmost derivation of the tree, in the order they were applied */
+    pub fn derivation(&self) -> Vec<usize> {
+        self.preorder().filter_map(|node| node.production).collect()
     }
-}
 
-impl<'a> IntoIterator for &'a LlkTree {
-    type Item = &'a char;
-    type IntoIter = LlkTreeIter<'a>;
+    pub fn has_errors(&self) -> bool {
+        self.nodes.iter().any(|node| node.is_error())
+    }
+
+    /*
+     * Checks a parse result against the grammar, see LlkGrammar::validate_tree.
+     * The root may be any non-terminal symbol, error nodes are allowed.
+     * Fails with the first violation
+     */
+    pub fn validate(&self, grammar: &LlkGrammar) -> Result<(), LlkError> {
+        match grammar.find_tree_violations(self, false).first() {
+            Some(violation) => Err(LlkError::InvalidTree(violation.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /* Nodes of the subtree are moved to the end of the arena under the root */
+    fn append_subtree(&mut self, subtree: LlkTree) {
+        let offset = self.nodes.len();
+        let shift = |id: LlkNodeId| LlkNodeId(id.0 + offset);
+
+        for mut node in subtree.nodes {
+            node.id = shift(node.id);
+            node.parent = Some(node.parent.map_or(self.root, shift));
+            node.children
+                .iter_mut()
+                .for_each(|child| *child = shift(*child));
+            self.nodes.push(node);
+        }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter()
+        self.nodes[self.root.0].children.push(shift(subtree.root));
     }
 }
 
-pub struct LlkTreeIter<'a> {
-    unvisited: Vec<(&'a LlkTree, usize)>,
+/*
+ * Listener building the parse tree. Nodes are added to the arena
+ * as they are entered, the non-terminal symbols being derived are
+ * kept on a stack to attach the following nodes to.
+ * Span of a non-terminal node starts with the first leaf following
+ * its entry and ends with the last leaf preceding its exit
+ */
+#[derive(Default)]
+pub struct LlkTreeBuilder {
+    nodes: Vec<LlkTreeNode>,
+    open_nodes: Vec<LlkNodeId>,
+    unstarted_nodes: Vec<LlkNodeId>,
+    last_end: LlkLocation,
+    labels: HashMap<usize, String>,
+    explicit_nodes: bool,
+    skip_end_marker: bool,
+    trailing_trivia: String,
+    orphan_leaves: Vec<(LlkNodeKind, LlkSpan)>,
 }
 
-impl<'a> LlkTreeIter<'a> {
-    fn lnr(&mut self, tree: &'a LlkTree, child_idx: usize) {
-        if let LlkTree::Node(node) = tree {
-            self.unvisited.push((tree, child_idx));
+impl LlkTreeBuilder {
+    pub fn new() -> LlkTreeBuilder {
+        LlkTreeBuilder::default()
+    }
 
-            if let Some(child) = node.children.get(self.unvisited.last().unwrap().1) {
-                self.unvisited.last_mut().unwrap().1 += 1;
-                self.lnr(child, 0);
-            }
-        } else {
-            self.unvisited.push((tree, 0));
+    /* Nodes get labels of their productions in the grammar */
+    pub fn with_production_labels(mut self, grammar: &LlkGrammar) -> LlkTreeBuilder {
+        self.labels = grammar.labels.clone();
+        self
+    }
+
+    /*
+     * Non-terminal nodes expanded by ε-productions get an ε child and the
+     * root gets the end marker leaf as its last child, so that the shape of
+     * the tree follows the derivation exactly
+     */
+    pub fn with_explicit_nodes(mut self) -> LlkTreeBuilder {
+        self.explicit_nodes = true;
+        self
+    }
+
+    /* Trees of fragments other than sentences have no end marker leaf */
+    pub(super) fn without_end_marker(mut self) -> LlkTreeBuilder {
+        self.skip_end_marker = true;
+        self
+    }
+
+    /* Nodes left open by a failed parsing are closed where they are */
+    pub fn into_tree(mut self) -> Option<LlkTree> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        self.start_nodes(self.last_end);
+        for id in std::mem::take(&mut self.open_nodes) {
+            self.nodes[id.0].span.end = self.last_end;
+        }
+
+        Some(LlkTree {
+            nodes: self.nodes,
+            root: LlkNodeId(0),
+            trailing_trivia: self.trailing_trivia,
+        })
+    }
+
+    fn push_node(
+        &mut self,
+        kind: LlkNodeKind,
+        span: LlkSpan,
+        production: Option<usize>,
+    ) -> Option<LlkNodeId> {
+        /* Nodes past the end of the root are attached to the root */
+        let parent = self
+            .open_nodes
+            .last()
+            .copied()
+            .or((!self.nodes.is_empty()).then_some(LlkNodeId(0)));
+
+        if parent.is_none() && !matches!(kind, LlkNodeKind::Nonterminal(_)) {
+            return None;
+        }
+
+        let id = LlkNodeId(self.nodes.len());
+        self.nodes.push(LlkTreeNode {
+            id,
+            kind,
+            span,
+            production,
+            label: production.and_then(|production| self.labels.get(&production).cloned()),
+            parent,
+            children: Vec::new(),
+        });
+
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+
+        Some(id)
+    }
+
+    fn push_leaf(&mut self, kind: LlkNodeKind, span: LlkSpan) {
+        /* Leaves preceding the root, e.g. skipped by error recovery, wait for it */
+        if self.nodes.is_empty() {
+            self.orphan_leaves.push((kind, span));
+            return;
+        }
+
+        self.start_nodes(span.start);
+        self.push_node(kind, span, None);
+        self.last_end = span.end;
+    }
+
+    /* Nodes entered since the last leaf start at the given location, the empty ones end there too */
+    fn start_nodes(&mut self, location: LlkLocation) {
+        for id in self.unstarted_nodes.drain(..) {
+            self.nodes[id.0].span = LlkSpan::empty(location);
         }
     }
 }
 
-impl<'a> Iterator for LlkTreeIter<'a> {
-    type Item = &'a char;
+impl LlkParseListener for LlkTreeBuilder {
+    fn enter_nonterminal(&mut self, nterm: char, production: Option<usize>) {
+        let kind = LlkNodeKind::Nonterminal(nterm);
 
-    fn next(&mut self) -> Option<&'a char> {
-        let tree_node = self.unvisited.pop()?.0;
+        if let Some(id) = self.push_node(kind, LlkSpan::default(), production) {
+            self.open_nodes.push(id);
+            self.unstarted_nodes.push(id);
+        }
 
-        match tree_node {
-            LlkTree::Leaf(symbol) => {
-                let (parent_tree, child_idx) = self
-                    .unvisited
-                    .pop()
-                    .expect("LlkTreeIter fatal error: tree leaf without parent node");
-                self.lnr(parent_tree, child_idx);
-                Some(symbol)
-            }
-            LlkTree::Node(node) => {
-                if let Some((parent_tree, child_idx)) = self.unvisited.pop() {
-                    self.lnr(parent_tree, child_idx);
-                }
+        /* Leaves preceding the root become its first children */
+        for (kind, span) in std::mem::take(&mut self.orphan_leaves) {
+            self.push_leaf(kind, span);
+        }
+    }
+
+    fn exit_nonterminal(&mut self, _nterm: char) {
+        let id = self
+            .open_nodes
+            .pop()
+            .expect("LlkTreeBuilder fatal error: exit without open node");
+
+        if !self.unstarted_nodes.contains(&id) {
+            self.nodes[id.0].span.end = self.last_end;
+        }
 
-                Some(&node.symbol)
+        /* Production without children can only be an ε-production */
+        let node = &self.nodes[id.0];
+        if self.explicit_nodes && node.production.is_some() && node.children.is_empty() {
+            self.open_nodes.push(id);
+            if let Some(epsilon) = self.push_node(LlkNodeKind::Epsilon, node.span, None) {
+                self.unstarted_nodes.push(epsilon);
             }
+            self.open_nodes.pop();
+        }
+    }
+
+    fn terminal(&mut self, symbol: char, span: LlkSpan, leading_trivia: &str) {
+        let kind = LlkNodeKind::Terminal {
+            symbol,
+            leading_trivia: leading_trivia.to_owned(),
+            trailing_trivia: String::default(),
+        };
+        self.push_leaf(kind, span);
+    }
+
+    fn error(&mut self, _error: &LlkError, skipped: &str, span: LlkSpan) {
+        self.push_leaf(LlkNodeKind::Error(skipped.to_owned()), span);
+    }
+
+    fn end_of_input(&mut self, location: LlkLocation, trailing_trivia: &str) {
+        self.start_nodes(location);
+
+        /*
+         * Trivia before the end of input follows the last leaf, nodes are added
+         * from left to right. Without leaves, e.g. in a sentence derived to ε,
+         * the root keeps it
+         */
+        let last_leaf = self
+            .nodes
+            .iter_mut()
+            .rev()
+            .find(|node| node.is_terminal() || node.is_error());
+
+        match last_leaf.map(|node| &mut node.kind) {
+            Some(LlkNodeKind::Terminal {
+                trailing_trivia: trivia,
+                ..
+            }) => trivia.push_str(trailing_trivia),
+            Some(LlkNodeKind::Error(skipped)) => skipped.push_str(trailing_trivia),
+            _ => self.trailing_trivia.push_str(trailing_trivia),
         }
+
+        /* End marker follows the derivation of the start symbol, it is the last child of the root */
+        if self.explicit_nodes && !self.skip_end_marker {
+            self.push_node(LlkNodeKind::Eof, LlkSpan::empty(location), None);
+        }
+    }
+}
+
+#[test]
+fn tree_navigation_test() {
+    use crate::grammar::expression_grammar;
+    use crate::grammar::parser::LlkParser;
+
+    let grammar = expression_grammar()
+        .with_labels(vec![(4, "parens".to_string())].drain(..).collect())
+        .unwrap();
+
+    let parser = LlkParser::new(grammar);
+    let tree = parser.parse("a+(a)").unwrap();
+
+    /* Nodes record the productions of the leftmost derivation */
+    assert_eq!(tree.derivation(), vec![0, 1, 5, 2, 4, 1, 5, 3, 3]);
+    assert_eq!(tree.production(tree.root()), Some(0));
+
+    let root = tree.root();
+    assert_eq!(tree.symbol(root), Some('S'));
+    assert_eq!(tree.parent(root), None);
+
+    /* Every node is a child of its parent */
+    for id in tree.node_ids().filter(|id| *id != root) {
+        assert!(tree.children(tree.parent(id).unwrap()).contains(&id));
+    }
+
+    let expr = tree.first_child(root).unwrap();
+    let term = tree.first_child(expr).unwrap();
+    let rest = tree.next_sibling(term).unwrap();
+    assert_eq!(tree.symbol(expr), Some('E'));
+    assert_eq!(
+        (tree.symbol(term), tree.symbol(rest)),
+        (Some('T'), Some('R'))
+    );
+    assert_eq!(tree.prev_sibling(rest), Some(term));
+    assert_eq!(
+        (tree.prev_sibling(term), tree.next_sibling(rest)),
+        (None, None)
+    );
+    assert_eq!(tree.last_child(expr), Some(rest));
+
+    let symbols =
+        |ids: &[LlkNodeId]| -> String { ids.iter().filter_map(|id| tree.symbol(*id)).collect() };
+    assert_eq!(symbols(tree.children(rest)), "+TR");
+
+    /* Terminal symbol inside the parentheses */
+    let parens = tree.children(rest)[1];
+    assert_eq!(tree.node(parens).production(), Some(4));
+    assert_eq!(tree.node(parens).label(), Some("parens"));
+    assert_eq!(tree.node(rest).label(), None);
+    let inner = tree.children(tree.children(parens)[1])[0];
+    let leaf = tree.first_child(inner).unwrap();
+    assert!(tree.node(leaf).is_terminal());
+    assert!(matches!(
+        tree.node(leaf).kind(),
+        LlkNodeKind::Terminal { symbol: 'a', .. }
+    ));
+    assert_eq!(symbols(&tree.ancestors(leaf).collect::<Vec<_>>()), "TETRES");
+
+    /* Side tables are indexed by node identifiers */
+    let mut depths = vec![0; tree.node_ids().count()];
+    for id in tree.node_ids() {
+        depths[id.index()] = tree.ancestors(id).count();
     }
+    assert_eq!(depths[leaf.index()], 6);
+    assert_eq!(depths.iter().filter(|depth| **depth == 0).count(), 1);
+
+    /* Error nodes are kept in the tree without a symbol */
+    let (tree, _errors) = parser.parse_with_recovery("a++a");
+    let error = tree
+        .node_ids()
+        .find(|id| tree.node(*id).is_error())
+        .unwrap();
+    assert_eq!((tree.symbol(error), tree.production(error)), (None, None));
+    assert!(tree.node(tree.parent(error).unwrap()).is_nonterminal());
+    assert_eq!(tree.source_text(), "a++a");
+}
+
+#[test]
+fn tree_span_test() {
+    use crate::grammar::char_class::LlkCharClass;
+    use crate::grammar::expression_grammar;
+    use crate::grammar::parser::LlkParser;
+    use crate::grammar::skip_rule::LlkSkipRule;
+
+    let grammar = expression_grammar()
+        .with_skip_rules(vec![LlkSkipRule::Class(
+            LlkCharClass::from_pattern("[ \n·]").unwrap(),
+        )])
+        .unwrap();
+
+    let parser = LlkParser::new(grammar);
+    let source = "a ·+\n (a) ";
+    let tree = parser.parse(source).unwrap();
+
+    let find = |symbol: char, text: &str| -> LlkNodeId {
+        tree.node_ids()
+            .find(|id| tree.symbol(*id) == Some(symbol) && tree.span(*id).text(source) == text)
+            .unwrap()
+    };
+
+    /* Trailing trivia is not covered by the root */
+    assert_eq!(tree.span(tree.root()).text(source), "a ·+\n (a)");
+
+    /* Offsets are in bytes, positions and columns in characters */
+    let plus = tree.span(find('+', "+"));
+    assert_eq!((plus.start.position, plus.start.offset), (3, 4));
+    assert_eq!((plus.start.line, plus.start.column), (1, 4));
+
+    let parens = tree.span(find('T', "(a)"));
+    assert_eq!((parens.start.line, parens.start.column), (2, 2));
+    assert_eq!(parens.len(), 3);
+
+    /* Non-terminal spans cover their children */
+    assert_eq!(tree.span(find('R', "+\n (a)")).start, plus.start);
+
+    /* Nodes derived to ε have an empty span before the following symbol */
+    let empty: Vec<LlkSpan> = tree
+        .node_ids()
+        .filter(|id| tree.children(*id).is_empty() && tree.node(*id).is_nonterminal())
+        .map(|id| tree.span(id))
+        .collect();
+    assert_eq!(empty.len(), 2);
+    assert!(empty.iter().all(|span| span.is_empty()));
+    assert_eq!(
+        empty
+            .iter()
+            .map(|span| span.start.offset)
+            .collect::<Vec<_>>(),
+        vec![9, 11]
+    );
+
+    /* Error nodes span the skipped symbols */
+    let (tree, _errors) = parser.parse_with_recovery("a++a");
+    let error = tree
+        .node_ids()
+        .find(|id| tree.node(*id).is_error())
+        .unwrap();
+    assert_eq!(tree.span(error).text("a++a"), "+");
+    assert_eq!(tree.span(error).start.position, 2);
+}
+
+#[test]
+fn explicit_nodes_test() {
+    use crate::grammar::char_class::LlkCharClass;
+    use crate::grammar::expression_grammar;
+    use crate::grammar::parser::LlkParser;
+    use crate::grammar::skip_rule::LlkSkipRule;
+
+    let create_grammar = || {
+        expression_grammar()
+            .with_skip_rules(vec![LlkSkipRule::Class(
+                LlkCharClass::from_pattern("[ ]").unwrap(),
+            )])
+            .unwrap()
+    };
+
+    let parser = LlkParser::new(create_grammar()).with_explicit_nodes();
+    let tree = parser.parse("a+a ").unwrap();
+    let plain_tree = LlkParser::new(create_grammar()).parse("a+a ").unwrap();
+
+    /* End marker closes the start symbol production */
+    let eof = tree.last_child(tree.root()).unwrap();
+    assert!(tree.node(eof).is_eof());
+    assert_eq!(tree.symbol(eof), None);
+    assert_eq!(tree.span(eof), LlkSpan::empty(tree.span(eof).start));
+    assert_eq!(tree.span(eof).start.offset, 4);
+
+    /* ε-production expands to a single ε child */
+    let epsilons: Vec<LlkNodeId> = tree
+        .node_ids()
+        .filter(|id| tree.node(*id).is_epsilon())
+        .collect();
+    assert_eq!(epsilons.len(), 1);
+    let rest = tree.parent(epsilons[0]).unwrap();
+    assert_eq!(
+        (tree.symbol(rest), tree.production(rest)),
+        (Some('R'), Some(3))
+    );
+    assert_eq!(tree.children(rest), &epsilons[..]);
+    assert_eq!(tree.span(epsilons[0]), tree.span(rest));
+    assert!(tree.span(rest).is_empty());
+
+    /* Everything else is the same as in the plain tree */
+    assert_eq!(tree.node_ids().count(), plain_tree.node_ids().count() + 2);
+    assert_eq!(tree.source_text(), plain_tree.source_text());
+    assert_eq!(tree.derivation(), plain_tree.derivation());
+    assert_eq!(
+        tree.iter()
+            .filter_map(|node| node.symbol())
+            .collect::<String>(),
+        plain_tree
+            .iter()
+            .filter_map(|node| node.symbol())
+            .collect::<String>()
+    );
+    assert!(plain_tree
+        .node_ids()
+        .all(|id| !plain_tree.node(id).is_epsilon() && !plain_tree.node(id).is_eof()));
+
+    /* End marker is not a part of other entry symbols */
+    let tree = parser.parse_as('R', "+a").unwrap();
+    assert!(tree.node_ids().all(|id| !tree.node(id).is_eof()));
+    assert_eq!(
+        tree.node_ids()
+            .filter(|id| tree.node(*id).is_epsilon())
+            .count(),
+        1
+    );
 }
@@ -0,0 +1,113 @@
+use super::{LlkNodeKind, LlkTree};
+use crate::grammar::dot::escape;
+
+/* Graphviz DOT rendering of parse trees */
+
+impl LlkTree {
+    /*
+     * Non-terminal nodes are ellipses, terminal leaves are boxes,
+     * error nodes are red octagons and explicit ε and end marker
+     * nodes are plain text. Children are kept in their order
+     */
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec![
+            "digraph tree {".to_string(),
+            "    ordering=out;".to_string(),
+        ];
+
+        for node in self.preorder() {
+            let attributes = match node.kind() {
+                LlkNodeKind::Nonterminal(nterm) => {
+                    format!("label=\"{}\", shape=ellipse", escape(&nterm.to_string()))
+                }
+                LlkNodeKind::Terminal { symbol, .. } => {
+                    format!("label=\"{}\", shape=box", escape(&symbol.to_string()))
+                }
+                LlkNodeKind::Error(skipped) => {
+                    format!("label=\"{}\", shape=octagon, color=red", escape(skipped))
+                }
+                LlkNodeKind::Epsilon => "label=\"ε\", shape=plaintext".to_string(),
+                LlkNodeKind::Eof => "label=\"EOF\", shape=plaintext".to_string(),
+            };
+
+            lines.push(format!("    n{} [{}];", node.id().index(), attributes));
+            if let Some(parent) = node.parent() {
+                lines.push(format!(
+                    "    n{} -> n{};",
+                    parent.index(),
+                    node.id().index()
+                ));
+            }
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
+#[test]
+fn tree_dot_test() {
+    use crate::grammar::parser::LlkParser;
+    use crate::grammar::LlkGrammar;
+
+    let create_grammar = || {
+        let term_symbols = vec!['a', '+', '(', ')', '"'].drain(..).collect();
+        let nterm_symbols = vec!['S', 'E', 'R', 'T'].drain(..).collect();
+        let start_symbol = 'S';
+        let lookahead = 1;
+        let productions = vec![
+            ('S', Some("E".to_string())),
+            ('E', Some("TR".to_string())),
+            ('R', Some("+TR".to_string())),
+            ('R', None),
+            ('T', Some("(E)".to_string())),
+            ('T', Some("a".to_string())),
+            ('T', Some("\"".to_string())),
+        ];
+
+        LlkGrammar::new(
+            term_symbols,
+            nterm_symbols,
+            start_symbol,
+            lookahead,
+            productions,
+        )
+        .unwrap()
+    };
+
+    let parser = LlkParser::new(create_grammar());
+    let tree = parser.parse("a+\"").unwrap();
+
+    assert_eq!(
+        tree.to_dot(),
+        [
+            "digraph tree {",
+            "    ordering=out;",
+            "    n0 [label=\"S\", shape=ellipse];",
+            "    n1 [label=\"E\", shape=ellipse];",
+            "    n0 -> n1;",
+            "    n2 [label=\"T\", shape=ellipse];",
+            "    n1 -> n2;",
+            "    n3 [label=\"a\", shape=box];",
+            "    n2 -> n3;",
+            "    n4 [label=\"R\", shape=ellipse];",
+            "    n1 -> n4;",
+            "    n5 [label=\"+\", shape=box];",
+            "    n4 -> n5;",
+            "    n6 [label=\"T\", shape=ellipse];",
+            "    n4 -> n6;",
+            "    n7 [label=\"\\\"\", shape=box];",
+            "    n6 -> n7;",
+            "    n8 [label=\"R\", shape=ellipse];",
+            "    n4 -> n8;",
+            "}",
+        ]
+        .join("\n")
+    );
+
+    /* Error nodes are marked */
+    let (tree, _errors) = parser.parse_with_recovery("a++a");
+    assert!(tree
+        .to_dot()
+        .contains("[label=\"+\", shape=octagon, color=red];"));
+}
@@ -0,0 +1,246 @@
+use std::fmt;
+
+use super::traversal::LlkTreeEvent;
+use super::{LlkNodeKind, LlkTree, LlkTreeNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlkTreeFormat {
+    /* Node per line indented by its depth */
+    Outline,
+    /* Node per line connected to its parent by box-drawing characters */
+    BoxDrawing,
+    /* Same as box-drawing with ASCII characters only */
+    Ascii,
+    /* Single line, non-terminal nodes are parenthesized with their children */
+    SExpression,
+}
+
+/*
+ * Renderer of trees as text. Non-terminal nodes are shown by their symbols,
+ * terminal leaves by their quoted symbols, error nodes by the skipped input.
+ * Productions and spans of the nodes are shown on demand
+ */
+pub struct LlkTreePrinter {
+    format: LlkTreeFormat,
+    show_productions: bool,
+    show_spans: bool,
+}
+
+impl LlkTreePrinter {
+    pub fn new(format: LlkTreeFormat) -> LlkTreePrinter {
+        LlkTreePrinter {
+            format,
+            show_productions: false,
+            show_spans: false,
+        }
+    }
+
+    /* Non-terminal nodes are followed by #index:label of their productions */
+    pub fn with_productions(mut self) -> LlkTreePrinter {
+        self.show_productions = true;
+        self
+    }
+
+    /* Nodes are followed by @line:column-line:column of their spans */
+    pub fn with_spans(mut self) -> LlkTreePrinter {
+        self.show_spans = true;
+        self
+    }
+
+    pub fn render(&self, tree: &LlkTree) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut sexpr = String::new();
+        /* Whether the open nodes are the last children of their parents */
+        let mut last_children: Vec<bool> = Vec::new();
+
+        for event in tree.events() {
+            match event {
+                LlkTreeEvent::Enter(node, depth) => {
+                    let text = self.format_node(node);
+
+                    match self.format {
+                        LlkTreeFormat::Outline => {
+                            lines.push(format!("{}{}", "  ".repeat(depth), text));
+                        }
+                        LlkTreeFormat::BoxDrawing | LlkTreeFormat::Ascii => {
+                            let (branch, last_branch, pipe) =
+                                if self.format == LlkTreeFormat::BoxDrawing {
+                                    ("├── ", "└── ", "│   ")
+                                } else {
+                                    ("|-- ", "`-- ", "|   ")
+                                };
+                            let is_last = tree.next_sibling(node.id()).is_none();
+
+                            /* Root has no branch, ancestors below it continue their pipes */
+                            let mut line: String = last_children
+                                .iter()
+                                .skip(1)
+                                .map(|last| if *last { "    " } else { pipe })
+                                .collect();
+                            if depth > 0 {
+                                line.push_str(if is_last { last_branch } else { branch });
+                            }
+                            line.push_str(&text);
+
+                            lines.push(line);
+                            last_children.push(is_last);
+                        }
+                        LlkTreeFormat::SExpression => {
+                            if depth > 0 {
+                                sexpr.push(' ');
+                            }
+                            if node.is_nonterminal() {
+                                sexpr.push('(');
+                            }
+                            sexpr.push_str(&text);
+                        }
+                    }
+                }
+                LlkTreeEvent::Exit(node, _depth) => {
+                    last_children.pop();
+
+                    if self.format == LlkTreeFormat::SExpression && node.is_nonterminal() {
+                        sexpr.push(')');
+                    }
+                }
+            }
+        }
+
+        if self.format == LlkTreeFormat::SExpression {
+            sexpr
+        } else {
+            lines.join("\n")
+        }
+    }
+
+    fn format_node(&self, node: &LlkTreeNode) -> String {
+        let mut text = match node.kind() {
+            LlkNodeKind::Nonterminal(nterm) => nterm.escape_default().to_string(),
+            LlkNodeKind::Terminal { symbol, .. } => format!("'{}'", symbol.escape_default()),
+            LlkNodeKind::Error(skipped) => format!("error \"{}\"", skipped.escape_default()),
+            LlkNodeKind::Epsilon => "ε".to_string(),
+            LlkNodeKind::Eof => "EOF".to_string(),
+        };
+
+        if self.show_productions {
+            if let Some(production) = node.production() {
+                text.push_str(&format!(" #{}", production));
+            }
+            if let Some(label) = node.label() {
+                text.push_str(&format!(":{}", label));
+            }
+        }
+
+        if self.show_spans {
+            let span = node.span();
+            text.push_str(&format!(
+                " @{}:{}-{}:{}",
+                span.start.line, span.start.column, span.end.line, span.end.column
+            ));
+        }
+
+        text
+    }
+}
+
+impl LlkTree {
+    pub fn render(&self, format: LlkTreeFormat) -> String {
+        LlkTreePrinter::new(format).render(self)
+    }
+}
+
+impl fmt::Display for LlkTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(LlkTreeFormat::BoxDrawing))
+    }
+}
+
+impl fmt::Debug for LlkTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(LlkTreeFormat::SExpression))
+    }
+}
+
+#[test]
+fn tree_printer_test() {
+    use crate::grammar::expression_grammar;
+    use crate::grammar::parser::LlkParser;
+    use crate::grammar::LlkGrammar;
+
+    let grammar = expression_grammar()
+        .with_labels(vec![(5, "atom".to_string())].drain(..).collect())
+        .unwrap();
+
+    let parser = LlkParser::new(grammar);
+    let tree = parser.parse("a+a").unwrap();
+
+    assert_eq!(
+        tree.render(LlkTreeFormat::Outline),
+        "S\n  E\n    T\n      'a'\n    R\n      '+'\n      T\n        'a'\n      R"
+    );
+
+    assert_eq!(
+        tree.to_string(),
+        [
+            "S",
+            "└── E",
+            "    ├── T",
+            "    │   └── 'a'",
+            "    └── R",
+            "        ├── '+'",
+            "        ├── T",
+            "        │   └── 'a'",
+            "        └── R",
+        ]
+        .join("\n")
+    );
+
+    assert_eq!(
+        tree.render(LlkTreeFormat::Ascii),
+        [
+            "S",
+            "`-- E",
+            "    |-- T",
+            "    |   `-- 'a'",
+            "    `-- R",
+            "        |-- '+'",
+            "        |-- T",
+            "        |   `-- 'a'",
+            "        `-- R",
+        ]
+        .join("\n")
+    );
+
+    assert_eq!(format!("{:?}", tree), "(S (E (T 'a') (R '+' (T 'a') (R))))");
+
+    /* Optional details of the nodes */
+    let printer = LlkTreePrinter::new(LlkTreeFormat::SExpression).with_productions();
+    assert_eq!(
+        printer.render(&tree),
+        "(S #0 (E #1 (T #5:atom 'a') (R #2 '+' (T #5:atom 'a') (R #3))))"
+    );
+
+    let printer = LlkTreePrinter::new(LlkTreeFormat::Outline).with_spans();
+    let tree = parser.parse_as('T', "a").unwrap();
+    assert_eq!(printer.render(&tree), "T @1:1-1:2\n  'a' @1:1-1:2");
+
+    /* Error, ε and end marker nodes */
+    let (tree, _errors) = parser.parse_with_recovery("a++a");
+    assert!(format!("{:?}", tree).contains("error \"+\""));
+
+    let parser = LlkParser::new(
+        LlkGrammar::new(
+            vec!['a'].drain(..).collect(),
+            vec!['S', 'R'].drain(..).collect(),
+            'S',
+            1,
+            vec![('S', Some("aR".to_string())), ('R', None)],
+        )
+        .unwrap(),
+    )
+    .with_explicit_nodes();
+    assert_eq!(
+        format!("{:?}", parser.parse("a").unwrap()),
+        "(S 'a' (R ε) EOF)"
+    );
+}
@@ -0,0 +1,859 @@
+use serde_json::{json, Map, Value as JsonValue};
+use std::iter::{Enumerate, Peekable};
+use std::str::Chars;
+
+use super::traversal::LlkTreeEvent;
+use super::{LlkNodeId, LlkNodeKind, LlkTree, LlkTreeNode};
+use crate::error::LlkError;
+use crate::grammar::span::{LlkLocation, LlkSpan};
+
+const ROOT_KEY: &str = "root";
+const NODES_KEY: &str = "nodes";
+const KIND_KEY: &str = "kind";
+const SYMBOL_KEY: &str = "symbol";
+const SKIPPED_KEY: &str = "skipped";
+const PRODUCTION_KEY: &str = "production";
+const LABEL_KEY: &str = "label";
+const LEADING_TRIVIA_KEY: &str = "leading_trivia";
+const TRAILING_TRIVIA_KEY: &str = "trailing_trivia";
+const SPAN_KEY: &str = "span";
+const SPAN_START_KEY: &str = "start";
+const SPAN_END_KEY: &str = "end";
+const POSITION_KEY: &str = "position";
+const OFFSET_KEY: &str = "offset";
+const LINE_KEY: &str = "line";
+const COLUMN_KEY: &str = "column";
+const CHILDREN_KEY: &str = "children";
+
+const NONTERMINAL_KIND: &str = "nonterminal";
+const TERMINAL_KIND: &str = "terminal";
+const ERROR_KIND: &str = "error";
+const EPSILON_KIND: &str = "epsilon";
+const EOF_KIND: &str = "eof";
+
+const PRODUCTION_KEYWORD: &str = ":production";
+const LABEL_KEYWORD: &str = ":label";
+const LEADING_TRIVIA_KEYWORD: &str = ":leading-trivia";
+const TRAILING_TRIVIA_KEYWORD: &str = ":trailing-trivia";
+const SPAN_KEYWORD: &str = ":span";
+
+/*
+ * Lossless serialization of trees. Kinds, spans, productions, labels
+ * and trivia of the nodes survive the round trip, so do node identifiers
+ * in JSON. Nodes are read without recursion, trees of any depth fit
+ */
+impl LlkTree {
+    /*
+     * Object with the root identifier and the array of nodes indexed by
+     * their identifiers, children are arrays of identifiers:
+     * {"root": 0, "nodes": [{"kind": "nonterminal", "symbol": "S",
+     * "production": 0, "span": {"start": {..}, "end": {..}},
+     * "children": [1]}, {"kind": "terminal", "symbol": "a", ..}]}.
+     * Trivia kept by the root goes to the "trailing_trivia" of the object
+     */
+    pub fn to_json(&self) -> String {
+        let nodes: Vec<JsonValue> = self.nodes.iter().map(node_to_json).collect();
+        let mut json_tree = json!({ ROOT_KEY: self.root.0, NODES_KEY: nodes });
+
+        if !self.trailing_trivia.is_empty() {
+            json_tree[TRAILING_TRIVIA_KEY] = json!(self.trailing_trivia);
+        }
+
+        json_tree.to_string()
+    }
+
+    /* Missing spans, productions, labels and trivia are left default */
+    pub fn from_json(json_string: &str) -> Result<LlkTree, LlkError> {
+        let json_values: JsonValue = serde_json::from_str(json_string)
+            .map_err(|_| deserialization_error("invalid JSON string"))?;
+
+        let json_nodes = json_values[NODES_KEY]
+            .as_array()
+            .ok_or_else(|| deserialization_error("invalid nodes format"))?;
+        let root = json_values[ROOT_KEY]
+            .as_u64()
+            .map(|root| root as usize)
+            .filter(|root| *root < json_nodes.len())
+            .ok_or_else(|| deserialization_error("invalid root format"))?;
+
+        let trailing_trivia = match &json_values[TRAILING_TRIVIA_KEY] {
+            JsonValue::Null => String::default(),
+            JsonValue::String(trivia) => trivia.clone(),
+            _ => return Err(deserialization_error("invalid trailing trivia format")),
+        };
+
+        let mut nodes = Vec::new();
+        for (index, json_node) in json_nodes.iter().enumerate() {
+            nodes.push(node_from_json(index, json_node, json_nodes.len())?);
+        }
+
+        let mut tree = LlkTree::from_linked_nodes(nodes, LlkNodeId(root))?;
+        tree.trailing_trivia = trailing_trivia;
+        Ok(tree)
+    }
+
+    /*
+     * Nested lists of the nodes in preorder, every node is a list of its
+     * kind, its symbol or skipped input, its attributes and its children:
+     * (nonterminal 'S' :production 0 :span (0 0 1 1 1 1 1 2)
+     * (terminal 'a' :span (0 0 1 1 1 1 1 2) :trailing-trivia " "))
+     * Spans list position, offset, line and column of both ends.
+     * Trivia kept by the root is the trailing trivia of the root
+     */
+    pub fn to_sexpr(&self) -> String {
+        let mut sexpr = String::new();
+
+        for event in self.events() {
+            match event {
+                LlkTreeEvent::Enter(node, depth) => {
+                    if depth > 0 {
+                        sexpr.push(' ');
+                    }
+                    sexpr.push('(');
+                    sexpr.push_str(&node_to_sexpr(node));
+
+                    if depth == 0 && !self.trailing_trivia.is_empty() {
+                        sexpr.push_str(&format!(
+                            " {} \"{}\"",
+                            TRAILING_TRIVIA_KEYWORD,
+                            self.trailing_trivia.escape_default()
+                        ));
+                    }
+                }
+                LlkTreeEvent::Exit(..) => sexpr.push(')'),
+            }
+        }
+
+        sexpr
+    }
+
+    /* Missing spans, productions, labels and trivia are left default */
+    pub fn from_sexpr(sexpr: &str) -> Result<LlkTree, LlkError> {
+        let mut reader = LlkSexprReader {
+            chars: sexpr.chars().enumerate().peekable(),
+        };
+        let mut nodes: Vec<LlkTreeNode> = Vec::new();
+        let mut open_nodes: Vec<LlkNodeId> = Vec::new();
+        let mut trailing_trivia = String::default();
+
+        loop {
+            match reader.next_token()? {
+                Some((LlkSexprToken::Open, _)) => {
+                    if !nodes.is_empty() && open_nodes.is_empty() {
+                        return Err(deserialization_error("more than one root node"));
+                    }
+
+                    let id = LlkNodeId(nodes.len());
+                    let parent = open_nodes.last().copied();
+                    nodes.push(reader.read_node(id, parent)?);
+                    if let Some(parent) = parent {
+                        nodes[parent.0].children.push(id);
+                    }
+                    open_nodes.push(id);
+                }
+                Some((LlkSexprToken::Close, position)) => {
+                    if open_nodes.pop().is_none() {
+                        return Err(deserialization_error(&format!(
+                            "unexpected ')' at position {}",
+                            position
+                        )));
+                    }
+                }
+                Some((LlkSexprToken::Keyword(keyword), position)) => {
+                    let id = open_nodes.last().copied().ok_or_else(|| {
+                        deserialization_error(&format!(
+                            "unexpected '{}' at position {}",
+                            keyword, position
+                        ))
+                    })?;
+
+                    /* Attributes go before the children */
+                    if !nodes[id.0].children.is_empty() {
+                        return Err(deserialization_error(&format!(
+                            "attribute '{}' after children at position {}",
+                            keyword, position
+                        )));
+                    }
+                    let root_trivia = (id.0 == 0).then_some(&mut trailing_trivia);
+                    reader.read_attribute(&mut nodes[id.0], root_trivia, &keyword, position)?;
+                }
+                Some((_token, position)) => {
+                    return Err(deserialization_error(&format!(
+                        "unexpected token at position {}",
+                        position
+                    )));
+                }
+                None => break,
+            }
+        }
+
+        if nodes.is_empty() || !open_nodes.is_empty() {
+            return Err(deserialization_error("unexpected end of input"));
+        }
+
+        Ok(LlkTree {
+            nodes,
+            root: LlkNodeId(0),
+            trailing_trivia,
+        })
+    }
+
+    /*
+     * Tree of the nodes linked by their children only. Every node except
+     * the root must be a child of exactly one node and reachable from the root
+     */
+    fn from_linked_nodes(
+        mut nodes: Vec<LlkTreeNode>,
+        root: LlkNodeId,
+    ) -> Result<LlkTree, LlkError> {
+        for index in 0..nodes.len() {
+            for child in nodes[index].children.clone() {
+                if child == root {
+                    return Err(deserialization_error("root node is a child"));
+                }
+                if nodes[child.0].parent.is_some() {
+                    return Err(deserialization_error(&format!(
+                        "node {} has more than one parent",
+                        child.0
+                    )));
+                }
+                nodes[child.0].parent = Some(LlkNodeId(index));
+            }
+        }
+
+        /* Nodes have one parent at most, so none is reached twice */
+        let mut reachable = 0;
+        let mut unvisited = vec![root];
+        while let Some(id) = unvisited.pop() {
+            reachable += 1;
+            unvisited.extend(&nodes[id.0].children);
+        }
+        if reachable != nodes.len() {
+            return Err(deserialization_error("nodes unreachable from the root"));
+        }
+
+        Ok(LlkTree {
+            nodes,
+            root,
+            trailing_trivia: String::default(),
+        })
+    }
+}
+
+fn node_to_json(node: &LlkTreeNode) -> JsonValue {
+    let mut json_node = Map::new();
+    let mut insert = |key: &str, value: JsonValue| json_node.insert(key.to_string(), value);
+
+    match &node.kind {
+        LlkNodeKind::Nonterminal(nterm) => {
+            insert(KIND_KEY, json!(NONTERMINAL_KIND));
+            insert(SYMBOL_KEY, json!(nterm.to_string()));
+        }
+        LlkNodeKind::Terminal {
+            symbol,
+            leading_trivia,
+            trailing_trivia,
+        } => {
+            insert(KIND_KEY, json!(TERMINAL_KIND));
+            insert(SYMBOL_KEY, json!(symbol.to_string()));
+            insert(LEADING_TRIVIA_KEY, json!(leading_trivia));
+            insert(TRAILING_TRIVIA_KEY, json!(trailing_trivia));
+        }
+        LlkNodeKind::Error(skipped) => {
+            insert(KIND_KEY, json!(ERROR_KIND));
+            insert(SKIPPED_KEY, json!(skipped));
+        }
+        LlkNodeKind::Epsilon => {
+            insert(KIND_KEY, json!(EPSILON_KIND));
+        }
+        LlkNodeKind::Eof => {
+            insert(KIND_KEY, json!(EOF_KIND));
+        }
+    }
+
+    if let Some(production) = node.production {
+        insert(PRODUCTION_KEY, json!(production));
+    }
+    if let Some(label) = &node.label {
+        insert(LABEL_KEY, json!(label));
+    }
+
+    let location_to_json = |location: LlkLocation| {
+        json!({
+            POSITION_KEY: location.position,
+            OFFSET_KEY: location.offset,
+            LINE_KEY: location.line,
+            COLUMN_KEY: location.column,
+        })
+    };
+    insert(
+        SPAN_KEY,
+        json!({
+            SPAN_START_KEY: location_to_json(node.span.start),
+            SPAN_END_KEY: location_to_json(node.span.end),
+        }),
+    );
+
+    let children: Vec<usize> = node.children.iter().map(|child| child.0).collect();
+    insert(CHILDREN_KEY, json!(children));
+
+    JsonValue::Object(json_node)
+}
+
+fn node_from_json(
+    index: usize,
+    json_node: &JsonValue,
+    len: usize,
+) -> Result<LlkTreeNode, LlkError> {
+    let invalid =
+        |what: &str| deserialization_error(&format!("invalid {} of node {}", what, index));
+
+    if !json_node.is_object() {
+        return Err(invalid("format"));
+    }
+
+    /* Optional string attribute */
+    let get_string = |key: &str, what: &str| -> Result<String, LlkError> {
+        match &json_node[key] {
+            JsonValue::Null => Ok(String::default()),
+            JsonValue::String(string) => Ok(string.clone()),
+            _ => Err(invalid(what)),
+        }
+    };
+    let get_symbol = || -> Result<char, LlkError> {
+        let symbol = json_node[SYMBOL_KEY].as_str().unwrap_or_default();
+        let mut chars = symbol.chars();
+        match (chars.next(), chars.next()) {
+            (Some(symbol), None) => Ok(symbol),
+            _ => Err(invalid("symbol")),
+        }
+    };
+
+    let kind = match json_node[KIND_KEY].as_str() {
+        Some(NONTERMINAL_KIND) => LlkNodeKind::Nonterminal(get_symbol()?),
+        Some(TERMINAL_KIND) => LlkNodeKind::Terminal {
+            symbol: get_symbol()?,
+            leading_trivia: get_string(LEADING_TRIVIA_KEY, "leading trivia")?,
+            trailing_trivia: get_string(TRAILING_TRIVIA_KEY, "trailing trivia")?,
+        },
+        Some(ERROR_KIND) => LlkNodeKind::Error(get_string(SKIPPED_KEY, "skipped input")?),
+        Some(EPSILON_KIND) => LlkNodeKind::Epsilon,
+        Some(EOF_KIND) => LlkNodeKind::Eof,
+        _ => return Err(invalid("kind")),
+    };
+
+    let production = match &json_node[PRODUCTION_KEY] {
+        JsonValue::Null => None,
+        production => Some(production.as_u64().ok_or_else(|| invalid("production"))? as usize),
+    };
+    let label = match &json_node[LABEL_KEY] {
+        JsonValue::Null => None,
+        _ => Some(get_string(LABEL_KEY, "label")?),
+    };
+
+    let location_from_json = |json_location: &JsonValue| -> Option<LlkLocation> {
+        let get = |key: &str| json_location[key].as_u64().map(|value| value as usize);
+
+        Some(LlkLocation {
+            position: get(POSITION_KEY)?,
+            offset: get(OFFSET_KEY)?,
+            line: get(LINE_KEY)?,
+            column: get(COLUMN_KEY)?,
+        })
+    };
+    let span = match &json_node[SPAN_KEY] {
+        JsonValue::Null => LlkSpan::default(),
+        json_span => checked_span(
+            location_from_json(&json_span[SPAN_START_KEY]).ok_or_else(|| invalid("span"))?,
+            location_from_json(&json_span[SPAN_END_KEY]).ok_or_else(|| invalid("span"))?,
+        )
+        .ok_or_else(|| invalid("span"))?,
+    };
+
+    let children = match &json_node[CHILDREN_KEY] {
+        JsonValue::Null => Vec::new(),
+        JsonValue::Array(children) => children
+            .iter()
+            .map(|child| {
+                child
+                    .as_u64()
+                    .map(|child| child as usize)
+                    .filter(|child| *child < len)
+                    .map(LlkNodeId)
+            })
+            .collect::<Option<Vec<LlkNodeId>>>()
+            .ok_or_else(|| invalid("children"))?,
+        _ => return Err(invalid("children")),
+    };
+
+    Ok(LlkTreeNode {
+        id: LlkNodeId(index),
+        kind,
+        span,
+        production,
+        label,
+        parent: None,
+        children,
+    })
+}
+
+/* Opening of the node list with its kind, symbol and attributes */
+fn node_to_sexpr(node: &LlkTreeNode) -> String {
+    let mut text = match &node.kind {
+        LlkNodeKind::Nonterminal(nterm) => {
+            format!("{} '{}'", NONTERMINAL_KIND, nterm.escape_default())
+        }
+        LlkNodeKind::Terminal { symbol, .. } => {
+            format!("{} '{}'", TERMINAL_KIND, symbol.escape_default())
+        }
+        LlkNodeKind::Error(skipped) => format!("{} \"{}\"", ERROR_KIND, skipped.escape_default()),
+        LlkNodeKind::Epsilon => EPSILON_KIND.to_string(),
+        LlkNodeKind::Eof => EOF_KIND.to_string(),
+    };
+
+    if let Some(production) = node.production {
+        text.push_str(&format!(" {} {}", PRODUCTION_KEYWORD, production));
+    }
+    if let Some(label) = &node.label {
+        text.push_str(&format!(
+            " {} \"{}\"",
+            LABEL_KEYWORD,
+            label.escape_default()
+        ));
+    }
+
+    let (start, end) = (node.span.start, node.span.end);
+    text.push_str(&format!(
+        " {} ({} {} {} {} {} {} {} {})",
+        SPAN_KEYWORD,
+        start.position,
+        start.offset,
+        start.line,
+        start.column,
+        end.position,
+        end.offset,
+        end.line,
+        end.column
+    ));
+
+    if let LlkNodeKind::Terminal {
+        leading_trivia,
+        trailing_trivia,
+        ..
+    } = &node.kind
+    {
+        if !leading_trivia.is_empty() {
+            text.push_str(&format!(
+                " {} \"{}\"",
+                LEADING_TRIVIA_KEYWORD,
+                leading_trivia.escape_default()
+            ));
+        }
+        if !trailing_trivia.is_empty() {
+            text.push_str(&format!(
+                " {} \"{}\"",
+                TRAILING_TRIVIA_KEYWORD,
+                trailing_trivia.escape_default()
+            ));
+        }
+    }
+
+    text
+}
+
+enum LlkSexprToken {
+    Open,
+    Close,
+    /* Atom starting with a colon */
+    Keyword(String),
+    Atom(String),
+    Char(char),
+    Str(String),
+}
+
+/* Tokens of an S-expression with the positions of their first characters, counted in characters */
+struct LlkSexprReader<'a> {
+    chars: Peekable<Enumerate<Chars<'a>>>,
+}
+
+impl<'a> LlkSexprReader<'a> {
+    fn next_token(&mut self) -> Result<Option<(LlkSexprToken, usize)>, LlkError> {
+        while self.chars.next_if(|(_, ch)| ch.is_whitespace()).is_some() {}
+
+        let (position, ch) = match self.chars.next() {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+
+        let token = match ch {
+            '(' => LlkSexprToken::Open,
+            ')' => LlkSexprToken::Close,
+            '\'' => {
+                let text = self.read_quoted('\'', position)?;
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(symbol), None) => LlkSexprToken::Char(symbol),
+                    _ => {
+                        return Err(deserialization_error(&format!(
+                            "invalid character at position {}",
+                            position
+                        )))
+                    }
+                }
+            }
+            '"' => LlkSexprToken::Str(self.read_quoted('"', position)?),
+            _ => {
+                let mut atom = ch.to_string();
+                while let Some((_, ch)) = self
+                    .chars
+                    .next_if(|(_, ch)| !ch.is_whitespace() && !"()'\"".contains(*ch))
+                {
+                    atom.push(ch);
+                }
+
+                if atom.starts_with(':') {
+                    LlkSexprToken::Keyword(atom)
+                } else {
+                    LlkSexprToken::Atom(atom)
+                }
+            }
+        };
+
+        Ok(Some((token, position)))
+    }
+
+    /* Text up to the closing quote with escapes as produced by escape_default */
+    fn read_quoted(&mut self, quote: char, position: usize) -> Result<String, LlkError> {
+        let invalid =
+            || deserialization_error(&format!("invalid quoted text at position {}", position));
+        let mut text = String::new();
+
+        loop {
+            let ch = match self.chars.next().ok_or_else(invalid)?.1 {
+                ch if ch == quote => return Ok(text),
+                '\\' => match self.chars.next().ok_or_else(invalid)?.1 {
+                    't' => '\t',
+                    'r' => '\r',
+                    'n' => '\n',
+                    '0' => '\0',
+                    'u' => {
+                        let mut hex = String::new();
+                        if self.chars.next().map(|(_, ch)| ch) != Some('{') {
+                            return Err(invalid());
+                        }
+                        loop {
+                            match self.chars.next().ok_or_else(invalid)?.1 {
+                                '}' => break,
+                                ch => hex.push(ch),
+                            }
+                        }
+                        u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(invalid)?
+                    }
+                    ch => ch,
+                },
+                ch => ch,
+            };
+
+            text.push(ch);
+        }
+    }
+
+    /* Node following an opening parenthesis up to its attributes */
+    fn read_node(
+        &mut self,
+        id: LlkNodeId,
+        parent: Option<LlkNodeId>,
+    ) -> Result<LlkTreeNode, LlkError> {
+        let (token, position) = self
+            .next_token()?
+            .ok_or_else(|| deserialization_error("unexpected end of input"))?;
+        let invalid = || deserialization_error(&format!("invalid node at position {}", position));
+
+        let kind = match token {
+            LlkSexprToken::Atom(kind) => match kind.as_str() {
+                NONTERMINAL_KIND => match self.next_token()? {
+                    Some((LlkSexprToken::Char(nterm), _)) => LlkNodeKind::Nonterminal(nterm),
+                    _ => return Err(invalid()),
+                },
+                TERMINAL_KIND => match self.next_token()? {
+                    Some((LlkSexprToken::Char(symbol), _)) => LlkNodeKind::Terminal {
+                        symbol,
+                        leading_trivia: String::default(),
+                        trailing_trivia: String::default(),
+                    },
+                    _ => return Err(invalid()),
+                },
+                ERROR_KIND => match self.next_token()? {
+                    Some((LlkSexprToken::Str(skipped), _)) => LlkNodeKind::Error(skipped),
+                    _ => return Err(invalid()),
+                },
+                EPSILON_KIND => LlkNodeKind::Epsilon,
+                EOF_KIND => LlkNodeKind::Eof,
+                _ => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        };
+
+        Ok(LlkTreeNode {
+            id,
+            kind,
+            span: LlkSpan::default(),
+            production: None,
+            label: None,
+            parent,
+            children: Vec::new(),
+        })
+    }
+
+    /* Trailing trivia of a non-terminal root is the one kept by the tree */
+    fn read_attribute(
+        &mut self,
+        node: &mut LlkTreeNode,
+        root_trivia: Option<&mut String>,
+        keyword: &str,
+        position: usize,
+    ) -> Result<(), LlkError> {
+        let invalid = || {
+            deserialization_error(&format!(
+                "invalid attribute '{}' at position {}",
+                keyword, position
+            ))
+        };
+        let token = self.next_token()?.map(|(token, _)| token);
+
+        match (keyword, token, &mut node.kind) {
+            (PRODUCTION_KEYWORD, Some(LlkSexprToken::Atom(production)), _) => {
+                node.production = Some(production.parse().map_err(|_| invalid())?);
+            }
+            (LABEL_KEYWORD, Some(LlkSexprToken::Str(label)), _) => {
+                node.label = Some(label);
+            }
+            (
+                LEADING_TRIVIA_KEYWORD,
+                Some(LlkSexprToken::Str(trivia)),
+                LlkNodeKind::Terminal { leading_trivia, .. },
+            ) => {
+                *leading_trivia = trivia;
+            }
+            (
+                TRAILING_TRIVIA_KEYWORD,
+                Some(LlkSexprToken::Str(trivia)),
+                LlkNodeKind::Terminal {
+                    trailing_trivia, ..
+                },
+            ) => {
+                *trailing_trivia = trivia;
+            }
+            (
+                TRAILING_TRIVIA_KEYWORD,
+                Some(LlkSexprToken::Str(trivia)),
+                LlkNodeKind::Nonterminal(_),
+            ) if root_trivia.is_some() => {
+                *root_trivia.unwrap() = trivia;
+            }
+            (SPAN_KEYWORD, Some(LlkSexprToken::Open), _) => {
+                let mut values = Vec::new();
+                loop {
+                    match self.next_token()?.map(|(token, _)| token) {
+                        Some(LlkSexprToken::Atom(value)) => {
+                            values.push(value.parse::<usize>().map_err(|_| invalid())?)
+                        }
+                        Some(LlkSexprToken::Close) => break,
+                        _ => return Err(invalid()),
+                    }
+                }
+
+                if values.len() != 8 {
+                    return Err(invalid());
+                }
+                let location = |values: &[usize]| LlkLocation {
+                    position: values[0],
+                    offset: values[1],
+                    line: values[2],
+                    column: values[3],
+                };
+                node.span = checked_span(location(&values[..4]), location(&values[4..]))
+                    .ok_or_else(invalid)?;
+            }
+            _ => return Err(invalid()),
+        }
+
+        Ok(())
+    }
+}
+
+/* Span may not end before it starts */
+fn checked_span(start: LlkLocation, end: LlkLocation) -> Option<LlkSpan> {
+    let ordered = start.position <= end.position
+        && start.offset <= end.offset
+        && (start.line, start.column) <= (end.line, end.column);
+
+    ordered.then(|| LlkSpan::new(start, end))
+}
+
+fn deserialization_error(description: &str) -> LlkError {
+    LlkError::TreeDeserializationFailed(description.to_string())
+}
+
+#[test]
+fn tree_serialization_test() {
+    use crate::grammar::char_class::LlkCharClass;
+    use crate::grammar::expression_grammar;
+    use crate::grammar::parser::LlkParser;
+    use crate::grammar::skip_rule::LlkSkipRule;
+
+    let create_grammar = || {
+        expression_grammar()
+            .with_skip_rules(vec![LlkSkipRule::Class(
+                LlkCharClass::from_pattern("[ \n\"]").unwrap(),
+            )])
+            .unwrap()
+            .with_labels(vec![(4, "parens".to_string())].drain(..).collect())
+            .unwrap()
+    };
+
+    let grammar = create_grammar();
+    let parser = LlkParser::new(create_grammar())
+        .with_trivia()
+        .with_explicit_nodes();
+    let source = " a +\n(\"a\") ";
+    let tree = parser.parse(source).unwrap();
+
+    /* Both formats survive the round trip with all the details */
+    let json = tree.to_json();
+    let from_json = LlkTree::from_json(&json).unwrap();
+    assert_eq!(from_json.to_json(), json);
+    assert_eq!(from_json.source_text(), source);
+
+    let sexpr = tree.to_sexpr();
+    let from_sexpr = LlkTree::from_sexpr(&sexpr).unwrap();
+    assert_eq!(from_sexpr.to_sexpr(), sexpr);
+    assert_eq!(from_sexpr.to_json(), json);
+    assert_eq!(
+        from_sexpr.render(super::LlkTreeFormat::Outline),
+        tree.render(super::LlkTreeFormat::Outline)
+    );
+
+    assert!(sexpr.starts_with("(nonterminal 'S' :production 0 :span (1 1 1 2 10 10 2 6) "));
+    assert!(sexpr
+        .contains("(nonterminal 'T' :production 4 :label \"parens\" :span (5 5 2 1 10 10 2 6) "));
+    assert!(sexpr.contains("(terminal 'a' :span (7 7 2 3 8 8 2 4) :leading-trivia \"\\\"\")"));
+    assert!(sexpr.ends_with(" (eof :span (11 11 2 7 11 11 2 7)))"));
+
+    let node = from_json.node(from_json.children(from_json.root())[0]);
+    assert_eq!(node.parent(), Some(from_json.root()));
+    assert_eq!(node.span(), tree.span(node.id()));
+
+    /* Deserialized trees are checked against their grammars */
+    assert!(from_json.validate(&grammar).is_ok());
+    assert!(from_sexpr.validate(&grammar).is_ok());
+
+    let tampered =
+        LlkTree::from_sexpr(&sexpr.replacen(":production 5", ":production 4", 1)).unwrap();
+    assert!(matches!(
+        tampered.validate(&grammar),
+        Err(LlkError::InvalidTree(_))
+    ));
+    let tampered = LlkTree::from_sexpr(&sexpr.replacen("'+'", "'-'", 1)).unwrap();
+    assert!(matches!(
+        tampered.validate(&grammar),
+        Err(LlkError::InvalidTree(_))
+    ));
+
+    /* Trees with errors round trip too */
+    let (tree, _errors) = parser.parse_with_recovery("a++a");
+    let from_sexpr = LlkTree::from_sexpr(&tree.to_sexpr()).unwrap();
+    assert!(from_sexpr.has_errors());
+    assert_eq!(from_sexpr.to_json(), tree.to_json());
+    assert!(from_sexpr.validate(&grammar).is_ok());
+
+    /* Hand-written trees may leave out the details, yet their children are checked */
+    let tree = LlkTree::from_sexpr("(nonterminal 'T' :production 5 (terminal 'a'))").unwrap();
+    assert!(tree.validate(&grammar).is_ok());
+    let tree = LlkTree::from_sexpr("(nonterminal 'T' (terminal 'a'))").unwrap();
+    assert!(tree.validate(&grammar).is_ok());
+    let tree =
+        LlkTree::from_sexpr("(nonterminal 'S' (nonterminal 'T' (terminal '+') (terminal '+')))")
+            .unwrap();
+    assert!(matches!(
+        tree.validate(&grammar),
+        Err(LlkError::InvalidTree(_))
+    ));
+    assert_eq!(tree.span(tree.root()), LlkSpan::default());
+    let tree = LlkTree::from_json(
+        r#"{"root": 1, "nodes": [
+            {"kind": "terminal", "symbol": "a"},
+            {"kind": "nonterminal", "symbol": "T", "production": 5, "children": [0]}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(tree.parent(LlkNodeId(0)), Some(tree.root()));
+    assert!(tree.validate(&grammar).is_ok());
+
+    /* Deep trees are read without recursion */
+    let source = vec!["a"; 2000].join("+");
+    let tree = LlkParser::new(create_grammar()).parse(&source).unwrap();
+    let from_sexpr = LlkTree::from_sexpr(&tree.to_sexpr()).unwrap();
+    assert_eq!(from_sexpr.source_text(), source);
+    assert_eq!(
+        LlkTree::from_json(&tree.to_json())
+            .unwrap()
+            .node_ids()
+            .count(),
+        tree.node_ids().count()
+    );
+
+    /* Malformed input */
+    let deserialization_failed = |result: Result<LlkTree, LlkError>| {
+        matches!(result, Err(LlkError::TreeDeserializationFailed(_)))
+    };
+
+    assert!(deserialization_failed(LlkTree::from_json("{")));
+    assert!(deserialization_failed(LlkTree::from_json(
+        r#"{"root": 0, "nodes": [{"kind": "nonterminal", "symbol": "S", "children": [0]}]}"#
+    )));
+    assert!(deserialization_failed(LlkTree::from_json(
+        r#"{"root": 0, "nodes": [{"kind": "nonterminal", "symbol": "S"}, {"kind": "epsilon"}]}"#
+    )));
+    assert!(deserialization_failed(LlkTree::from_json(
+        r#"{"root": 0, "nodes": [{"kind": "leaf", "symbol": "a"}]}"#
+    )));
+    assert!(deserialization_failed(LlkTree::from_sexpr("")));
+    assert!(deserialization_failed(LlkTree::from_sexpr(
+        "(nonterminal 'S'"
+    )));
+    assert!(deserialization_failed(LlkTree::from_sexpr(
+        "(nonterminal 'S') (eof)"
+    )));
+    assert!(deserialization_failed(LlkTree::from_sexpr(
+        "(nonterminal \"S\")"
+    )));
+    assert!(deserialization_failed(LlkTree::from_sexpr(
+        "(nonterminal 'S' (epsilon) :production 3)"
+    )));
+    assert!(deserialization_failed(LlkTree::from_sexpr(
+        "(eof :span (1 2 3))"
+    )));
+
+    /* Spans may not be inverted */
+    assert!(deserialization_failed(LlkTree::from_sexpr(
+        "(nonterminal 'T' :span (1 1 1 2 0 0 1 1))"
+    )));
+    assert!(deserialization_failed(LlkTree::from_json(
+        r#"{"root": 0, "nodes": [{"kind": "nonterminal", "symbol": "T", "span": {
+            "start": {"position": 1, "offset": 3, "line": 1, "column": 2},
+            "end": {"position": 2, "offset": 2, "line": 1, "column": 3}
+        }}]}"#
+    )));
+
+    /* Errors report positions in characters */
+    match LlkTree::from_sexpr("(nonterminal 'Ж' :юж 1)") {
+        Err(LlkError::TreeDeserializationFailed(description)) => {
+            assert!(description.ends_with("at position 17"))
+        }
+        _ => panic!("invalid attribute is accepted"),
+    }
+}
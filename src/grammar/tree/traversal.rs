@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+
+use super::{LlkNodeId, LlkTree, LlkTreeNode};
+
+/* Node entered or exited during a depth-first traversal, the root is at depth 0 */
+#[derive(Clone, Copy)]
+pub enum LlkTreeEvent<'a> {
+    Enter(&'a LlkTreeNode, usize),
+    Exit(&'a LlkTreeNode, usize),
+}
+
+impl<'a> LlkTreeEvent<'a> {
+    pub fn node(&self) -> &'a LlkTreeNode {
+        match self {
+            LlkTreeEvent::Enter(node, _depth) | LlkTreeEvent::Exit(node, _depth) => node,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        match self {
+            LlkTreeEvent::Enter(_node, depth) | LlkTreeEvent::Exit(_node, depth) => *depth,
+        }
+    }
+}
+
+/*
+ * Depth-first traversal reporting every node when it is entered and
+ * when it is exited, leaves included. Visited nodes are kept on a stack
+ * with the index of their next child, none for a node not entered yet
+ */
+pub struct LlkTreeEvents<'a> {
+    tree: &'a LlkTree,
+    unvisited: Vec<(LlkNodeId, Option<usize>)>,
+}
+
+impl<'a> Iterator for LlkTreeEvents<'a> {
+    type Item = LlkTreeEvent<'a>;
+
+    fn next(&mut self) -> Option<LlkTreeEvent<'a>> {
+        let depth = self.unvisited.len().checked_sub(1)?;
+        let (id, child_idx) = self.unvisited.last_mut()?;
+        let node = self.tree.node(*id);
+
+        match child_idx {
+            None => {
+                *child_idx = Some(0);
+                Some(LlkTreeEvent::Enter(node, depth))
+            }
+            Some(idx) if *idx < node.children.len() => {
+                let child = node.children[*idx];
+                *idx += 1;
+                self.unvisited.push((child, None));
+                self.next()
+            }
+            Some(_) => {
+                self.unvisited.pop();
+                Some(LlkTreeEvent::Exit(node, depth))
+            }
+        }
+    }
+}
+
+impl LlkTree {
+    pub fn events(&self) -> LlkTreeEvents<'_> {
+        LlkTreeEvents {
+            tree: self,
+            unvisited: vec![(self.root, None)],
+        }
+    }
+
+    pub fn preorder(&self) -> impl Iterator<Item = &LlkTreeNode> {
+        self.events().filter_map(|event| match event {
+            LlkTreeEvent::Enter(node, _depth) => Some(node),
+            LlkTreeEvent::Exit(..) => None,
+        })
+    }
+
+    pub fn postorder(&self) -> impl Iterator<Item = &LlkTreeNode> {
+        self.events().filter_map(|event| match event {
+            LlkTreeEvent::Exit(node, _depth) => Some(node),
+            LlkTreeEvent::Enter(..) => None,
+        })
+    }
+
+    /* Nodes level by level, each level from left to right */
+    pub fn breadth_first(&self) -> impl Iterator<Item = &LlkTreeNode> {
+        let mut unvisited = VecDeque::from([self.root]);
+
+        std::iter::from_fn(move || {
+            let node = self.node(unvisited.pop_front()?);
+            unvisited.extend(&node.children);
+            Some(node)
+        })
+    }
+
+    /* Terminal leaves from left to right, i.e. the yield of the tree */
+    pub fn leaves(&self) -> impl Iterator<Item = &LlkTreeNode> {
+        self.preorder().filter(|node| node.is_terminal())
+    }
+
+    /* Nodes of the tree in postorder */
+    pub fn iter(&self) -> LlkTreeIter<'_> {
+        LlkTreeIter {
+            events: self.events(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a LlkTree {
+    type Item = &'a LlkTreeNode;
+    type IntoIter = LlkTreeIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/* Nodes of the tree in postorder */
+pub struct LlkTreeIter<'a> {
+    events: LlkTreeEvents<'a>,
+}
+
+impl<'a> Iterator for LlkTreeIter<'a> {
+    type Item = &'a LlkTreeNode;
+
+    fn next(&mut self) -> Option<&'a LlkTreeNode> {
+        self.events.find_map(|event| match event {
+            LlkTreeEvent::Exit(node, _depth) => Some(node),
+            LlkTreeEvent::Enter(..) => None,
+        })
+    }
+}
+
+#[test]
+fn tree_traversal_test() {
+    use crate::grammar::expression_grammar;
+    use crate::grammar::parser::LlkParser;
+
+    let grammar = expression_grammar();
+
+    let parser = LlkParser::new(grammar);
+    let tree = parser.parse("a+(a)").unwrap();
+
+    let symbols = |nodes: &mut dyn Iterator<Item = &LlkTreeNode>| -> String {
+        nodes.filter_map(|node| node.symbol()).collect()
+    };
+
+    assert_eq!(symbols(&mut tree.preorder()), "SETaR+T(ETaR)R");
+    assert_eq!(symbols(&mut tree.postorder()), "aT+(aTRE)TRRES");
+    assert_eq!(symbols(&mut tree.breadth_first()), "SETRa+TR(E)TRa");
+    assert_eq!(symbols(&mut tree.leaves()), "a+(a)");
+    assert_eq!(symbols(&mut tree.iter()), symbols(&mut tree.postorder()));
+    assert_eq!(tree.iter().count(), tree.node_ids().count());
+
+    /* Every node is visited once by every traversal */
+    assert_eq!(tree.preorder().count(), tree.node_ids().count());
+    assert_eq!(tree.breadth_first().count(), tree.node_ids().count());
+
+    /* Nodes know their identifiers, so traversals can be combined with navigation */
+    assert!(tree
+        .leaves()
+        .all(|leaf| tree.node(tree.parent(leaf.id()).unwrap()).is_nonterminal()));
+
+    /* Events nest properly and report depths of the nodes */
+    let mut open: Vec<LlkNodeId> = Vec::new();
+    let mut outline = String::new();
+
+    for event in tree.events() {
+        match event {
+            LlkTreeEvent::Enter(node, depth) => {
+                assert_eq!(depth, open.len());
+                assert_eq!(tree.ancestors(node.id()).count(), depth);
+                open.push(node.id());
+                outline.push_str(&format!("{}{}", depth, node.symbol().unwrap()));
+            }
+            LlkTreeEvent::Exit(node, depth) => {
+                assert_eq!(open.pop(), Some(node.id()));
+                assert_eq!(depth, open.len());
+            }
+        }
+    }
+    assert!(open.is_empty());
+    assert_eq!(outline, "0S1E2T3a2R3+3T4(4E5T6a5R4)3R");
+    assert_eq!(tree.events().map(|event| event.depth()).max(), Some(6));
+}
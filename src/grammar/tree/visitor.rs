@@ -0,0 +1,263 @@
+use super::traversal::LlkTreeEvent;
+use super::{LlkNodeKind, LlkTree, LlkTreeNode};
+
+/*
+ * Visitor of the tree nodes, starting with the root. Non-terminal nodes
+ * expanded by a labeled production go to visit_labeled, the others
+ * and the labeled ones by default go to visit_symbol, which walks
+ * their children. An overriding method decides whether to walk them
+ */
+pub trait LlkTreeVisitor {
+    fn visit(&mut self, tree: &LlkTree, node: &LlkTreeNode) {
+        match node.kind() {
+            LlkNodeKind::Nonterminal(nterm) => match node.label() {
+                Some(label) => self.visit_labeled(label, tree, node),
+                None => self.visit_symbol(*nterm, tree, node),
+            },
+            LlkNodeKind::Terminal { symbol, .. } => self.visit_terminal(*symbol, tree, node),
+            LlkNodeKind::Error(skipped) => self.visit_error(skipped, tree, node),
+            LlkNodeKind::Epsilon | LlkNodeKind::Eof => (),
+        }
+    }
+
+    fn visit_labeled(&mut self, _label: &str, tree: &LlkTree, node: &LlkTreeNode) {
+        if let Some(nterm) = node.symbol() {
+            self.visit_symbol(nterm, tree, node);
+        }
+    }
+
+    fn visit_symbol(&mut self, _nterm: char, tree: &LlkTree, node: &LlkTreeNode) {
+        self.walk_children(tree, node);
+    }
+
+    fn visit_terminal(&mut self, _symbol: char, _tree: &LlkTree, _node: &LlkTreeNode) {}
+
+    fn visit_error(&mut self, _skipped: &str, _tree: &LlkTree, _node: &LlkTreeNode) {}
+
+    fn walk_children(&mut self, tree: &LlkTree, node: &LlkTreeNode) {
+        for child in node.children() {
+            self.visit(tree, tree.node(*child));
+        }
+    }
+}
+
+/*
+ * Fold computing a value of every node from the values of its children,
+ * bottom-up. Dispatch of non-terminal nodes is the same as by the visitor.
+ * Folding into LlkTree::from_node rebuilds the tree, possibly transformed
+ */
+pub trait LlkTreeFold {
+    type Value;
+
+    fn fold_terminal(&mut self, symbol: char, tree: &LlkTree, node: &LlkTreeNode) -> Self::Value;
+
+    fn fold_symbol(
+        &mut self,
+        nterm: char,
+        tree: &LlkTree,
+        node: &LlkTreeNode,
+        children: Vec<Self::Value>,
+    ) -> Self::Value;
+
+    fn fold_labeled(
+        &mut self,
+        _label: &str,
+        tree: &LlkTree,
+        node: &LlkTreeNode,
+        children: Vec<Self::Value>,
+    ) -> Self::Value {
+        self.fold_symbol(node.symbol().unwrap_or_default(), tree, node, children)
+    }
+
+    /* Error nodes and explicit ε and end marker nodes are left out of the values of their parents by default */
+    fn fold_other(&mut self, _tree: &LlkTree, _node: &LlkTreeNode) -> Option<Self::Value> {
+        None
+    }
+}
+
+impl LlkTree {
+    pub fn accept<V: LlkTreeVisitor>(&self, visitor: &mut V) {
+        visitor.visit(self, self.node(self.root));
+    }
+
+    /* None only if the root itself is left out by fold_other */
+    pub fn fold<F: LlkTreeFold>(&self, folder: &mut F) -> Option<F::Value> {
+        let mut values: Vec<Vec<F::Value>> = vec![Vec::new()];
+
+        for event in self.events() {
+            match event {
+                LlkTreeEvent::Enter(..) => values.push(Vec::new()),
+                LlkTreeEvent::Exit(node, _depth) => {
+                    let children = values
+                        .pop()
+                        .expect("LlkTree fatal error: fold exit without enter");
+
+                    let value = match node.kind() {
+                        LlkNodeKind::Nonterminal(nterm) => Some(match node.label() {
+                            Some(label) => folder.fold_labeled(label, self, node, children),
+                            None => folder.fold_symbol(*nterm, self, node, children),
+                        }),
+                        LlkNodeKind::Terminal { symbol, .. } => {
+                            Some(folder.fold_terminal(*symbol, self, node))
+                        }
+                        _ => folder.fold_other(self, node),
+                    };
+
+                    values.last_mut().unwrap().extend(value);
+                }
+            }
+        }
+
+        values.pop().and_then(|mut values| values.pop())
+    }
+}
+
+#[test]
+fn tree_visitor_test() {
+    use crate::grammar::char_class::LlkCharClass;
+    use crate::grammar::expression_grammar;
+    use crate::grammar::parser::LlkParser;
+
+    /* Sum of digits with parentheses */
+    let grammar = expression_grammar()
+        .with_char_classes(
+            vec![('a', LlkCharClass::from_pattern("[0-9]").unwrap())]
+                .drain(..)
+                .collect(),
+        )
+        .unwrap()
+        .with_labels(vec![(4, "parens".to_string())].drain(..).collect())
+        .unwrap();
+
+    let parser = LlkParser::new(grammar);
+    let tree = parser.parse("1+(2+(3))").unwrap();
+
+    /* Visitor collecting digits outside of the parentheses */
+    #[derive(Default)]
+    struct OuterDigits {
+        digits: String,
+        nterms: String,
+        parens: usize,
+    }
+
+    impl LlkTreeVisitor for OuterDigits {
+        fn visit_labeled(&mut self, label: &str, _tree: &LlkTree, _node: &LlkTreeNode) {
+            assert_eq!(label, "parens");
+            self.parens += 1;
+        }
+
+        fn visit_symbol(&mut self, nterm: char, tree: &LlkTree, node: &LlkTreeNode) {
+            self.nterms.push(nterm);
+            self.walk_children(tree, node);
+        }
+
+        fn visit_terminal(&mut self, symbol: char, _tree: &LlkTree, _node: &LlkTreeNode) {
+            if symbol.is_ascii_digit() {
+                self.digits.push(symbol);
+            }
+        }
+    }
+
+    let mut visitor = OuterDigits::default();
+    tree.accept(&mut visitor);
+    assert_eq!(
+        (
+            visitor.digits.as_str(),
+            visitor.nterms.as_str(),
+            visitor.parens
+        ),
+        ("1", "SETRR", 1)
+    );
+
+    /* Fold computing the sum and the nesting depth of parentheses */
+    struct Sum;
+
+    impl LlkTreeFold for Sum {
+        type Value = (u32, usize);
+
+        fn fold_terminal(
+            &mut self,
+            symbol: char,
+            _tree: &LlkTree,
+            _node: &LlkTreeNode,
+        ) -> (u32, usize) {
+            (symbol.to_digit(10).unwrap_or_default(), 0)
+        }
+
+        fn fold_symbol(
+            &mut self,
+            _nterm: char,
+            _tree: &LlkTree,
+            _node: &LlkTreeNode,
+            children: Vec<(u32, usize)>,
+        ) -> (u32, usize) {
+            children.iter().fold((0, 0), |(sum, depth), child| {
+                (sum + child.0, depth.max(child.1))
+            })
+        }
+
+        fn fold_labeled(
+            &mut self,
+            _label: &str,
+            _tree: &LlkTree,
+            _node: &LlkTreeNode,
+            children: Vec<(u32, usize)>,
+        ) -> (u32, usize) {
+            (children[1].0, children[1].1 + 1)
+        }
+    }
+
+    assert_eq!(tree.fold(&mut Sum), Some((6, 2)));
+
+    /* Fold rebuilding the tree without ε nodes and chains of single non-terminal nodes */
+    struct Compact;
+
+    impl LlkTreeFold for Compact {
+        type Value = LlkTree;
+
+        fn fold_terminal(&mut self, _symbol: char, _tree: &LlkTree, node: &LlkTreeNode) -> LlkTree {
+            LlkTree::from_node(node, Vec::new())
+        }
+
+        fn fold_symbol(
+            &mut self,
+            _nterm: char,
+            _tree: &LlkTree,
+            node: &LlkTreeNode,
+            mut children: Vec<LlkTree>,
+        ) -> LlkTree {
+            children.retain(|child| {
+                child.node_ids().count() > 1 || !child.node(child.root()).is_nonterminal()
+            });
+
+            if children.len() == 1 && children[0].node(children[0].root()).is_nonterminal() {
+                children.pop().unwrap()
+            } else {
+                LlkTree::from_node(node, children)
+            }
+        }
+    }
+
+    let compact = tree.fold(&mut Compact).unwrap();
+    assert_eq!(compact.source_text(), "1+(2+(3))");
+    assert_eq!(
+        compact
+            .preorder()
+            .filter_map(|node| node.symbol())
+            .collect::<String>(),
+        "ET1R+T(ET2R+T(T3))"
+    );
+    assert_eq!(compact.derivation(), vec![1, 5, 2, 4, 1, 5, 2, 4, 5]);
+    assert_eq!(
+        compact.node(compact.root()).span(),
+        tree.node(tree.root()).span()
+    );
+
+    /* Rebuilt tree is consistent */
+    for id in compact.node_ids() {
+        assert_eq!(compact.node(id).id(), id);
+        for child in compact.children(id) {
+            assert_eq!(compact.parent(*child), Some(id));
+        }
+    }
+}
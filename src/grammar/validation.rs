@@ -0,0 +1,389 @@
+use std::fmt;
+
+use super::span::LlkSpan;
+use super::tree::{LlkNodeId, LlkNodeKind, LlkTree, LlkTreeNode};
+use super::LlkGrammar;
+
+/*
+ * Violation of the grammar by a tree node. Path leads to the node from
+ * the root by indices of children, span is the one of the node or, for
+ * symbols missing from a production, the empty one at the end of its parent
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LlkTreeViolation {
+    pub node: LlkNodeId,
+    pub path: Vec<usize>,
+    pub span: LlkSpan,
+    pub description: String,
+}
+
+impl LlkTreeViolation {
+    fn new(
+        tree: &LlkTree,
+        node: LlkNodeId,
+        span: LlkSpan,
+        description: String,
+    ) -> LlkTreeViolation {
+        let mut path: Vec<usize> = std::iter::once(node)
+            .chain(tree.ancestors(node))
+            .filter_map(|id| {
+                let siblings = tree.children(tree.parent(id)?);
+                siblings.iter().position(|sibling| *sibling == id)
+            })
+            .collect();
+        path.reverse();
+
+        LlkTreeViolation {
+            node,
+            path,
+            span,
+            description,
+        }
+    }
+}
+
+impl fmt::Display for LlkTreeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let path: Vec<String> = self.path.iter().map(|index| index.to_string()).collect();
+
+        write!(
+            f,
+            "{}:{}: {} (node {} at /{})",
+            self.span.start.line,
+            self.span.start.column,
+            self.description,
+            self.node.index(),
+            path.join("/")
+        )
+    }
+}
+
+impl LlkGrammar {
+    /*
+     * Checks that the tree is a derivation of the start symbol: the root is
+     * the start symbol, every non-terminal node is expanded by a production of
+     * its symbol, the recorded one if any, and the leaves are terminals of the
+     * grammar, ε and end marker nodes in their places. Violations are returned
+     * in preorder, a valid tree has none
+     */
+    pub fn validate_tree(&self, tree: &LlkTree) -> Vec<LlkTreeViolation> {
+        self.find_tree_violations(tree, true)
+    }
+
+    /*
+     * Unless strict, any non-terminal symbol may be the root and error nodes
+     * are allowed. Nodes with error children are never matched against
+     * productions, recovery skips and drops symbols there
+     */
+    pub(super) fn find_tree_violations(
+        &self,
+        tree: &LlkTree,
+        strict: bool,
+    ) -> Vec<LlkTreeViolation> {
+        let mut violations = Vec::new();
+        let violation = |id: LlkNodeId, description: String| {
+            LlkTreeViolation::new(tree, id, tree.span(id), description)
+        };
+
+        let root = tree.node(tree.root());
+        match root.kind() {
+            LlkNodeKind::Nonterminal(nterm) if strict && *nterm != self.start_symbol => {
+                violations.push(violation(
+                    root.id(),
+                    format!(
+                        "root is '{}' instead of the start symbol '{}'",
+                        nterm, self.start_symbol
+                    ),
+                ));
+            }
+            LlkNodeKind::Nonterminal(_) => (),
+            _ => violations.push(violation(
+                root.id(),
+                "root is not a non-terminal node".to_string(),
+            )),
+        }
+
+        for node in tree.preorder() {
+            let id = node.id();
+
+            match node.kind() {
+                LlkNodeKind::Nonterminal(nterm) if !self.is_nterm(*nterm) => {
+                    violations.push(violation(
+                        id,
+                        format!("unknown non-terminal symbol '{}'", nterm),
+                    ));
+                }
+                LlkNodeKind::Nonterminal(nterm) => {
+                    let matched = !node
+                        .children()
+                        .iter()
+                        .any(|child| tree.node(*child).is_error());
+
+                    match node.production() {
+                        Some(production) if production >= self.productions.len() => {
+                            violations
+                                .push(violation(id, format!("unknown production {}", production)));
+                        }
+                        Some(production) if self.productions[production].0 != *nterm => {
+                            violations.push(violation(
+                                id,
+                                format!(
+                                    "production {} expands '{}' instead of '{}'",
+                                    LlkGrammar::format_production(&self.productions[production]),
+                                    self.productions[production].0,
+                                    nterm
+                                ),
+                            ));
+                        }
+                        Some(production) => {
+                            if node.label() != self.production_label(production) {
+                                violations.push(violation(
+                                    id,
+                                    format!(
+                                        "label does not match production {}",
+                                        LlkGrammar::format_production(
+                                            &self.productions[production]
+                                        )
+                                    ),
+                                ));
+                            }
+                            if matched {
+                                violations
+                                    .extend(self.find_child_violation(tree, node, production));
+                            }
+                        }
+                        None => {
+                            if node.label().is_some() {
+                                violations
+                                    .push(violation(id, "label without production".to_string()));
+                            }
+                            if matched
+                                && !(0..self.productions.len())
+                                    .filter(|production| self.productions[*production].0 == *nterm)
+                                    .any(|production| {
+                                        self.find_child_violation(tree, node, production).is_none()
+                                    })
+                            {
+                                violations.push(violation(
+                                    id,
+                                    format!("children match no production of '{}'", nterm),
+                                ));
+                            }
+                        }
+                    }
+                }
+                LlkNodeKind::Terminal { symbol, .. }
+                    if !self
+                        .term_symbols
+                        .iter()
+                        .any(|term| self.term_matches(*term, *symbol)) =>
+                {
+                    violations.push(violation(
+                        id,
+                        format!("unknown terminal symbol '{}'", symbol),
+                    ));
+                }
+                LlkNodeKind::Error(skipped) if strict => {
+                    violations.push(violation(
+                        id,
+                        format!("error node skipping \"{}\"", skipped),
+                    ));
+                }
+                _ => (),
+            }
+
+            if !node.is_nonterminal() {
+                if node.production().is_some() || node.label().is_some() {
+                    violations.push(violation(id, "production of a leaf".to_string()));
+                }
+                if !node.children().is_empty() {
+                    violations.push(violation(id, "leaf with children".to_string()));
+                }
+            }
+        }
+
+        violations
+    }
+
+    /*
+     * First child not matching the right-hand side of the production.
+     * ε child of an ε-production and the end marker following the
+     * children of the root are optional
+     */
+    fn find_child_violation(
+        &self,
+        tree: &LlkTree,
+        node: &LlkTreeNode,
+        production: usize,
+    ) -> Option<LlkTreeViolation> {
+        let mut children = node.children();
+        let rhs: Vec<char> = self.productions[production]
+            .1
+            .as_deref()
+            .unwrap_or_default()
+            .chars()
+            .collect();
+
+        if node.id() == tree.root() {
+            if let [rest @ .., last] = children {
+                if tree.node(*last).is_eof() {
+                    children = rest;
+                }
+            }
+        }
+
+        if rhs.is_empty() {
+            if let [child] = children {
+                if tree.node(*child).is_epsilon() {
+                    return None;
+                }
+            }
+        }
+
+        for index in 0..rhs.len().max(children.len()) {
+            let violation = match (rhs.get(index), children.get(index)) {
+                (Some(expected), Some(child))
+                    if !self.child_matches(*expected, tree.node(*child)) =>
+                {
+                    (*child, tree.span(*child), "expected", Some(*expected))
+                }
+                (Some(expected), None) => (
+                    node.id(),
+                    LlkSpan::empty(node.span().end),
+                    "missing",
+                    Some(*expected),
+                ),
+                (None, Some(child)) => (*child, tree.span(*child), "unexpected child", None),
+                _ => continue,
+            };
+
+            let (id, span, what, expected) = violation;
+            let description = match expected {
+                Some(expected) => format!("{} '{}'", what, expected),
+                None => what.to_string(),
+            };
+
+            return Some(LlkTreeViolation::new(
+                tree,
+                id,
+                span,
+                format!(
+                    "{} of production {}",
+                    description,
+                    LlkGrammar::format_production(&self.productions[production])
+                ),
+            ));
+        }
+
+        None
+    }
+
+    fn child_matches(&self, expected: char, child: &LlkTreeNode) -> bool {
+        match child.kind() {
+            LlkNodeKind::Nonterminal(nterm) => *nterm == expected,
+            LlkNodeKind::Terminal { symbol, .. } => {
+                self.is_term(expected) && self.term_matches(expected, *symbol)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[test]
+fn validate_tree_test() {
+    use crate::error::LlkError;
+    use crate::grammar::expression_grammar;
+    use crate::grammar::parser::LlkParser;
+
+    let grammar = expression_grammar();
+    let parser = LlkParser::new(expression_grammar());
+
+    /* Parse trees are valid, with explicit nodes too */
+    let tree = parser.parse("a+(a)").unwrap();
+    assert_eq!(grammar.validate_tree(&tree), vec![]);
+    let tree = LlkParser::new(expression_grammar())
+        .with_explicit_nodes()
+        .parse("a+(a)")
+        .unwrap();
+    assert_eq!(grammar.validate_tree(&tree), vec![]);
+
+    /* Root must be the start symbol */
+    let tree = parser.parse_as('T', "a").unwrap();
+    let violations = grammar.validate_tree(&tree);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(
+        (violations[0].node, violations[0].path.as_slice()),
+        (tree.root(), &[][..])
+    );
+    assert!(tree.validate(&grammar).is_ok());
+
+    /* Children not matching their production are located precisely */
+    let tree = LlkTree::from_sexpr(
+        "(nonterminal 'S' :production 0 (nonterminal 'E' :production 1 \
+         (nonterminal 'T' :production 5 (terminal 'a')) \
+         (nonterminal 'R' :production 2 (terminal '+') \
+         (terminal 'a' :span (2 2 1 3 3 3 1 4)) (nonterminal 'R' :production 3))))",
+    )
+    .unwrap();
+    let violations = grammar.validate_tree(&tree);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].path, vec![0, 1, 1]);
+    assert_eq!(tree.symbol(violations[0].node), Some('a'));
+    assert_eq!(
+        violations[0].to_string(),
+        "1:3: expected 'T' of production (R -> +TR) (node 6 at /0/1/1)"
+    );
+    assert!(matches!(
+        tree.validate(&grammar),
+        Err(LlkError::InvalidTree(_))
+    ));
+
+    /* Missing symbols are reported at the end of their parent, all violations are collected */
+    let tree = LlkTree::from_sexpr(
+        "(nonterminal 'S' :production 0 (nonterminal 'E' :production 1 \
+         (nonterminal 'T' (terminal '+')) \
+         (nonterminal 'R' :production 2 :span (1 1 1 2 2 2 1 3) (terminal '+'))))",
+    )
+    .unwrap();
+    let violations = grammar.validate_tree(&tree);
+    assert_eq!(
+        violations
+            .iter()
+            .map(|violation| (violation.path.clone(), violation.description.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            (vec![0, 0], "children match no production of 'T'"),
+            (vec![0, 1], "missing 'T' of production (R -> +TR)"),
+        ]
+    );
+    assert_eq!(
+        violations[1].span,
+        LlkSpan::empty(tree.span(violations[1].node).end)
+    );
+
+    /* Leaves must be terminals of the grammar */
+    let tree = LlkTree::from_sexpr(
+        "(nonterminal 'S' :production 0 (nonterminal 'E' :production 1 \
+         (nonterminal 'T' :production 5 (terminal 'b')) (nonterminal 'R')))",
+    )
+    .unwrap();
+    let violations = grammar.validate_tree(&tree);
+    assert_eq!(violations.len(), 2);
+    assert_eq!(
+        violations[0].description,
+        "expected 'a' of production (T -> a)"
+    );
+    assert_eq!(violations[1].description, "unknown terminal symbol 'b'");
+    assert!(violations
+        .iter()
+        .all(|violation| violation.path == vec![0, 0, 0]));
+
+    /* Error nodes are violations, unless validating a parse result */
+    let (tree, _errors) = parser.parse_with_recovery("a++a");
+    let violations = grammar.validate_tree(&tree);
+    assert!(!violations.is_empty());
+    assert!(violations
+        .iter()
+        .all(|violation| tree.node(violation.node).is_error()));
+    assert!(tree.validate(&grammar).is_ok());
+}
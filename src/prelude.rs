@@ -1,3 +1,11 @@
 pub use super::{
-    error::LlkError, grammar::parser::LlkParser, grammar::tree::LlkTree, grammar::LlkGrammar,
+    error::LlkError, grammar::actions::LlkActions, grammar::attributes::LlkAttributeGrammar,
+    grammar::attributes::LlkAttributeKind, grammar::char_class::LlkCharClass,
+    grammar::completion::LlkCompletion, grammar::listener::LlkParseListener,
+    grammar::parser::LlkParseSession, grammar::parser::LlkParser, grammar::skip_rule::LlkSkipRule,
+    grammar::span::LlkLocation, grammar::span::LlkSpan, grammar::tree::LlkNodeId,
+    grammar::tree::LlkNodeKind, grammar::tree::LlkTree, grammar::tree::LlkTreeBuilder,
+    grammar::tree::LlkTreeEvent, grammar::tree::LlkTreeFold, grammar::tree::LlkTreeFormat,
+    grammar::tree::LlkTreePrinter, grammar::tree::LlkTreeVisitor,
+    grammar::validation::LlkTreeViolation, grammar::LlkGrammar,
 };